@@ -0,0 +1,297 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! An output that records the session as an [asciicast v2] file, as played by asciinema.
+//!
+//! [asciicast v2]: https://docs.asciinema.org/manual/asciicast/v2/
+
+use std::any::Any;
+use std::fmt::Write as _;
+use std::io;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::Dimensions;
+use crate::SuperConsole;
+use crate::error::OutputError;
+use crate::output::OutputTarget;
+use crate::output::SuperConsoleOutput;
+
+/// Tees every frame to an asciicast v2 recording, then forwards it to an inner output.
+///
+/// Frames to the main output are recorded as `"o"` events, and auxiliary output as `"a"` events,
+/// which players ignore. Changes of the size the console draws at are recorded as `"r"` events. The header records that
+/// size when the recording starts, resolved like the console does.
+pub struct AsciicastOutput {
+    inner: Box<dyn SuperConsoleOutput>,
+    /// Used when the inner output can't tell the terminal size, like the console's.
+    fallback_size: Option<Dimensions>,
+    /// Behind a mutex, as resizes are noticed through `terminal_size`.
+    recorder: Mutex<Recorder>,
+}
+
+struct Recorder {
+    writer: Box<dyn Write + Send + Sync>,
+    start: Instant,
+    size: Dimensions,
+}
+
+impl AsciicastOutput {
+    /// Starts the recording by writing its header to `writer`. `fallback_size` is the size the
+    /// console falls back to, see [`Builder::build_forced`](crate::Builder::build_forced).
+    pub fn new(
+        inner: Box<dyn SuperConsoleOutput>,
+        mut writer: Box<dyn Write + Send + Sync>,
+        fallback_size: Option<Dimensions>,
+    ) -> Result<Self, OutputError> {
+        let size = SuperConsole::resolve_size(fallback_size, || inner.terminal_size())?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        writeln!(
+            writer,
+            r#"{{"version": 2, "width": {}, "height": {}, "timestamp": {timestamp}}}"#,
+            size.width, size.height
+        )
+        .map_err(OutputError::Write)?;
+
+        Ok(Self {
+            inner,
+            fallback_size,
+            recorder: Mutex::new(Recorder {
+                writer,
+                start: Instant::now(),
+                size,
+            }),
+        })
+    }
+
+    fn record(&self, code: &str, data: &str) -> Result<(), OutputError> {
+        self.recorder
+            .lock()
+            .unwrap()
+            .event(code, data)
+            .map_err(OutputError::Write)
+    }
+}
+
+impl Recorder {
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(self.writer, "[{time:.6}, \"{code}\", \"{}\"]", escape(data))
+    }
+}
+
+/// Escapes `text` to be put in a JSON string.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl SuperConsoleOutput for AsciicastOutput {
+    fn should_render(&mut self) -> bool {
+        self.inner.should_render()
+    }
+
+    fn output(&mut self, buffer: Vec<u8>) -> Result<(), OutputError> {
+        self.record("o", &String::from_utf8_lossy(&buffer))?;
+        self.inner.output(buffer)
+    }
+
+    fn output_to(&mut self, buffer: Vec<u8>, target: OutputTarget) -> Result<(), OutputError> {
+        let code = match target {
+            OutputTarget::Main => "o",
+            OutputTarget::Aux => "a",
+        };
+        self.record(code, &String::from_utf8_lossy(&buffer))?;
+        self.inner.output_to(buffer, target)
+    }
+
+    fn flush(&mut self) -> Result<(), OutputError> {
+        self.recorder
+            .get_mut()
+            .unwrap()
+            .writer
+            .flush()
+            .map_err(OutputError::Write)?;
+        self.inner.flush()
+    }
+
+    fn aux_stream_is_tty(&self) -> bool {
+        self.inner.aux_stream_is_tty()
+    }
+
+    fn terminal_size(&self) -> Result<Dimensions, OutputError> {
+        let size = SuperConsole::resolve_size(self.fallback_size, || self.inner.terminal_size())?;
+        let mut recorder = self.recorder.lock().unwrap();
+        if size != recorder.size {
+            recorder.size = size;
+            recorder
+                .event("r", &format!("{}x{}", size.width, size.height))
+                .map_err(OutputError::Write)?;
+        }
+        Ok(size)
+    }
+
+    fn finalize(self: Box<Self>) -> Result<(), OutputError> {
+        let mut recorder = self.recorder.into_inner().unwrap();
+        recorder.writer.flush().map_err(OutputError::Write)?;
+        self.inner.finalize()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+    use crate::Lines;
+    use crate::components::echo::Echo;
+    use crate::testing::SharedWriter;
+    use crate::testing::TestOutput;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+    use crate::testing::test_console_aux_incompatible;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a \"quote\" \\ \x1b[1m\n\t\u{7}é"),
+            r#"a \"quote\" \\ \u001b[1m\n\t\u0007é"#
+        );
+    }
+
+    #[test]
+    fn test_recording() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let inner = test_console().output.take().unwrap();
+        let mut output = AsciicastOutput::new(inner, Box::new(writer.clone()), None)?;
+
+        output.output(b"main\x1b[K\n".to_vec())?;
+        output.output_to(b"aux\n".to_vec(), OutputTarget::Aux)?;
+        output
+            .inner
+            .as_any_mut()
+            .downcast_mut::<TestOutput>()
+            .unwrap()
+            .terminal_size = Dimensions::new(100, 30);
+        assert_eq!(output.terminal_size()?, Dimensions::new(100, 30));
+        output.terminal_size()?;
+        Box::new(output).finalize()?;
+
+        let recording = String::from_utf8(writer.0.lock().unwrap().clone())?;
+        let lines: Vec<_> = recording.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with(r#"{"version": 2, "width": 80, "height": 80, "timestamp": "#));
+        assert!(lines[1].starts_with('['));
+        assert!(lines[1].ends_with(r#", "o", "main\u001b[K\n"]"#));
+        assert!(lines[2].ends_with(r#", "a", "aux\n"]"#));
+        assert!(lines[3].ends_with(r#", "r", "100x30"]"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recording_without_terminal() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut inner = test_console_aux_incompatible().output.take().unwrap();
+        // What outputs report when there is no terminal to ask.
+        inner
+            .as_any_mut()
+            .downcast_mut::<TestOutput>()
+            .unwrap()
+            .terminal_size = Dimensions::default();
+        let mut output = AsciicastOutput::new(
+            inner,
+            Box::new(writer.clone()),
+            Some(Dimensions::new(100, 30)),
+        )?;
+
+        output.output_to(b"aux\n".to_vec(), OutputTarget::Aux)?;
+        assert_eq!(output.terminal_size()?, Dimensions::new(100, 30));
+        Box::new(output).finalize()?;
+
+        // The fallback size isn't a resize.
+        let recording = String::from_utf8(writer.0.lock().unwrap().clone())?;
+        let lines: Vec<_> = recording.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"version": 2, "width": 100, "height": 30, "#));
+        assert!(lines[1].ends_with(r#", "a", "aux\n"]"#));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_console_frames_are_forwarded() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut console = test_console();
+        let inner = console.output.take().unwrap();
+        console.output = Some(Box::new(AsciicastOutput::new(
+            inner,
+            Box::new(writer.clone()),
+            None,
+        )?));
+
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+        let output: &AsciicastOutput = console
+            .output
+            .as_deref()
+            .unwrap()
+            .as_any()
+            .downcast_ref()
+            .unwrap();
+        let inner: &TestOutput = output.inner.as_any().downcast_ref().unwrap();
+        assert_eq!(inner.frames.len(), 1);
+        assert!(String::from_utf8(writer.0.lock().unwrap().clone())?.contains("state"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder() -> anyhow::Result<()> {
+        let terminal = SharedWriter::default();
+        let recording = SharedWriter::default();
+        let mut builder = Builder::new();
+        builder
+            .write_to(Box::new(terminal.clone()))
+            .record_asciicast(Box::new(recording.clone()));
+        let console = builder.build_forced(Dimensions::new(80, 24))?;
+        console.finalize(&Echo(Lines(vec![vec!["done"].try_into()?])))?;
+
+        assert!(frame_contains(&terminal.0.lock().unwrap(), "done"));
+        let recording = String::from_utf8(recording.0.lock().unwrap().clone())?;
+        assert!(recording.starts_with(r#"{"version": 2, "#));
+        assert!(recording.contains("done"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,453 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! A tokio-native render loop, and an output that writes through [`AsyncWrite`] streams so that
+//! rendering never blocks the runtime.
+//!
+//! ```ignore
+//! let (output, output_task) = AsyncSuperConsoleOutput::stdio();
+//! let mut builder = Builder::new();
+//! builder.custom_output(Box::new(output));
+//! let console = builder.build()?.unwrap();
+//!
+//! let driver = AsyncRenderDriver::spawn(console, 30, move || snapshot());
+//! driver.emit_sender().emit(lines).await;
+//! driver.finish().await?;
+//! output_task.wait().await;
+//! ```
+
+use std::any::Any;
+use std::io;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use crossbeam_channel::Receiver;
+use crossterm::tty::IsTty;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+use crate::Lines;
+use crate::SuperConsole;
+use crate::components::Component;
+use crate::error::Error;
+use crate::error::OutputError;
+use crate::output::OutputTarget;
+use crate::output::SuperConsoleOutput;
+
+/// How many messages an [`EmitSender`] can queue before `emit` waits for the driver.
+const EMIT_QUEUE_CAPACITY: usize = 1024;
+
+type AsyncStream = Box<dyn AsyncWrite + Send + Unpin + 'static>;
+
+/// An output writing through [`AsyncWrite`] streams from a tokio task, the async equivalent of
+/// the output used by [`Builder::non_blocking`](crate::Builder::non_blocking). This makes a few
+/// guarantees:
+///
+/// - Calls to output() never block. After should_render() returned true, they don't queue more
+///   than two frames.
+/// - When the [`OutputTask`] completes after finalize(), the last frame passed to output() is shown.
+/// - When an error occurs, the next fallible call will return it.
+pub struct AsyncSuperConsoleOutput {
+    /// A channel to send frames for writing.
+    sender: mpsc::UnboundedSender<(Vec<u8>, OutputTarget)>,
+    /// Number of frames sent to the writer task and not fully written yet.
+    pending: Arc<AtomicUsize>,
+    /// A channel back for errors encountered by the writer task.
+    errors: Receiver<OutputError>,
+    /// The auxillary output is compatible with tty
+    aux_compatible: bool,
+}
+
+/// The task writing out the frames of an [`AsyncSuperConsoleOutput`].
+pub struct OutputTask(JoinHandle<()>);
+
+impl OutputTask {
+    /// Waits until every frame was written, which happens after the console has been finalized.
+    pub async fn wait(self) {
+        if let Err(e) = self.0.await
+            && let Ok(panic) = e.try_into_panic()
+        {
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+impl AsyncSuperConsoleOutput {
+    /// Writes to tokio's stderr, and auxiliary output to tokio's stdout.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn stdio() -> (Self, OutputTask) {
+        Self::new(
+            Box::new(tokio::io::stderr()),
+            Box::new(tokio::io::stdout()),
+            io::stdout().is_tty(),
+        )
+    }
+
+    /// Spawns a task on the current tokio runtime writing frames to `stream`, and auxiliary output
+    /// to `aux_stream`.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn new(
+        mut stream: AsyncStream,
+        mut aux_stream: AsyncStream,
+        aux_is_tty: bool,
+    ) -> (Self, OutputTask) {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<(Vec<u8>, OutputTarget)>();
+        let (error_sender, errors) = crossbeam_channel::unbounded::<OutputError>();
+        let pending = Arc::new(AtomicUsize::new(0));
+
+        let handle = tokio::spawn({
+            let pending = pending.clone();
+            async move {
+                while let Some((data, output_target)) = receiver.recv().await {
+                    let out_stream = match output_target {
+                        OutputTarget::Main => &mut stream,
+                        OutputTarget::Aux => &mut aux_stream,
+                    };
+                    let res = match out_stream.write_all(&data).await {
+                        Ok(()) => out_stream.flush().await,
+                        Err(e) => Err(e),
+                    };
+                    pending.fetch_sub(1, Ordering::SeqCst);
+                    if let Err(e) = res {
+                        // This can only fail if the sender disconnected, in which case they'll
+                        // stop sending us data momentarily, so ignore the failure.
+                        let _ignored = error_sender.try_send(OutputError::Write(e));
+                    }
+                }
+            }
+        });
+
+        (
+            Self {
+                sender,
+                pending,
+                errors,
+                aux_compatible: aux_is_tty,
+            },
+            OutputTask(handle),
+        )
+    }
+}
+
+impl SuperConsoleOutput for AsyncSuperConsoleOutput {
+    /// Refuse to produce further output if the writer task already has 2 buffered frames (one
+    /// queued, one it's currently writing out).
+    fn should_render(&mut self) -> bool {
+        !self.errors.is_empty() || self.pending.load(Ordering::SeqCst) < 2
+    }
+
+    fn output(&mut self, buffer: Vec<u8>) -> Result<(), OutputError> {
+        self.output_to(buffer, OutputTarget::Main)
+    }
+
+    fn output_to(&mut self, buffer: Vec<u8>, target: OutputTarget) -> Result<(), OutputError> {
+        if let Ok(err) = self.errors.try_recv() {
+            return Err(err);
+        }
+
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.sender
+            .send((buffer, target))
+            .expect("Superconsole output task crashed");
+
+        Ok(())
+    }
+
+    fn aux_stream_is_tty(&self) -> bool {
+        self.aux_compatible
+    }
+
+    /// Notify the writer task that no further writes are expected. This does not wait for the
+    /// frames to be written, await the [`OutputTask`] for that.
+    fn finalize(self: Box<Self>) -> Result<(), OutputError> {
+        let Self {
+            sender,
+            pending: _,
+            errors,
+            aux_compatible: _,
+        } = *self;
+        drop(sender);
+
+        match errors.try_recv() {
+            Ok(err) => Err(err),
+            Err(_) => Ok(()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+enum Message {
+    Emit(Lines),
+    EmitAux(Lines),
+    Finish,
+}
+
+/// Applies a message received by the render loop, `None` meaning that every sender is gone.
+fn handle_message(console: &mut SuperConsole, message: Option<Message>) -> ControlFlow<()> {
+    match message {
+        Some(Message::Emit(lines)) => console.emit(lines),
+        Some(Message::EmitAux(lines)) => console.emit_aux(lines),
+        Some(Message::Finish) | None => return ControlFlow::Break(()),
+    }
+    ControlFlow::Continue(())
+}
+
+/// A cloneable sender that queues lines to be emitted by an [`AsyncRenderDriver`].
+///
+/// Lines emitted after the driver has stopped are discarded.
+#[derive(Clone)]
+pub struct EmitSender {
+    sender: mpsc::Sender<Message>,
+}
+
+impl EmitSender {
+    /// Queues the passed lines to be drawn on the next frame, waiting if the queue is full.
+    pub async fn emit(&self, lines: Lines) {
+        let _ignored = self.sender.send(Message::Emit(lines)).await;
+    }
+
+    /// Queues the passed lines of auxillary output to be drawn on the next frame, waiting if the
+    /// queue is full.
+    pub async fn emit_aux(&self, lines: Lines) {
+        let _ignored = self.sender.send(Message::EmitAux(lines)).await;
+    }
+}
+
+/// Owns a [`SuperConsole`] in a tokio task, and renders it at a fixed frame rate.
+///
+/// This is the async equivalent of [`RenderDriver`](crate::driver::RenderDriver). Rendering
+/// happens on the runtime, so the console should write through an [`AsyncSuperConsoleOutput`].
+pub struct AsyncRenderDriver<E> {
+    emit_sender: EmitSender,
+    task: JoinHandle<Result<(), Error<E>>>,
+}
+
+impl<E: Send + 'static> AsyncRenderDriver<E> {
+    /// Spawns a task on the current tokio runtime, rendering `frames_per_second` times per second.
+    /// A rate of 0 is treated as 1.
+    pub fn spawn<F, C>(console: SuperConsole, frames_per_second: u32, factory: F) -> Self
+    where
+        F: FnMut() -> C + Send + 'static,
+        C: Component<Error = E>,
+    {
+        let period = Duration::from_secs(1) / frames_per_second.max(1);
+        let (sender, mut receiver) = mpsc::channel(EMIT_QUEUE_CAPACITY);
+
+        let task = tokio::spawn(async move {
+            let mut console = console;
+            let mut factory = factory;
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            'render: loop {
+                tokio::select! {
+                    // Check the tick first, so that a steady stream of lines can't starve rendering.
+                    biased;
+                    _ = interval.tick() => {
+                        // Drain the lines queued so far into this frame, but not the ones that
+                        // keep arriving while doing so.
+                        for _ in 0..receiver.len() {
+                            if handle_message(&mut console, receiver.try_recv().ok()).is_break() {
+                                break 'render;
+                            }
+                        }
+                        console.render(&factory())?;
+                    }
+                    message = receiver.recv() => {
+                        if handle_message(&mut console, message).is_break() {
+                            break;
+                        }
+                    }
+                }
+            }
+            console.finalize(&factory())
+        });
+
+        Self {
+            emit_sender: EmitSender { sender },
+            task,
+        }
+    }
+
+    /// Returns a sender that can be moved to other tasks to emit lines.
+    pub fn emit_sender(&self) -> EmitSender {
+        self.emit_sender.clone()
+    }
+
+    /// Stops the render loop after draining the lines emitted so far, and performs the final
+    /// render with [`DrawMode::Final`](crate::DrawMode::Final).
+    ///
+    /// Returns the first error encountered by the render loop, if any.
+    pub async fn finish(self) -> Result<(), Error<E>> {
+        let _ignored = self.emit_sender.sender.send(Message::Finish).await;
+        match self.task.await {
+            Ok(res) => res,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::Builder;
+    use crate::Dimensions;
+    use crate::DrawMode;
+    use crate::testing::frame_contains;
+
+    struct State(&'static str);
+
+    impl Component for State {
+        type Error = Infallible;
+
+        fn draw_unchecked(
+            &self,
+            _dimensions: Dimensions,
+            mode: DrawMode,
+        ) -> Result<Lines, Infallible> {
+            let text = match mode {
+                DrawMode::Normal => self.0,
+                DrawMode::Final => "done",
+            };
+            Ok(Lines(vec![vec![text].try_into().unwrap()]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_driver() -> anyhow::Result<()> {
+        let (stream, mut reader) = tokio::io::duplex(1 << 20);
+        let (output, output_task) =
+            AsyncSuperConsoleOutput::new(Box::new(stream), Box::new(tokio::io::sink()), false);
+        let mut builder = Builder::new();
+        builder.custom_output(Box::new(output));
+        let console = builder.build_forced(Dimensions::new(80, 24))?;
+
+        let frames = Arc::new(AtomicUsize::new(0));
+        let driver = AsyncRenderDriver::spawn(console, 100, {
+            let frames = frames.clone();
+            move || {
+                frames.fetch_add(1, Ordering::SeqCst);
+                State("working")
+            }
+        });
+        let sender = driver.emit_sender();
+        tokio::spawn(async move {
+            for i in 0..10 {
+                sender
+                    .emit(Lines(vec![vec![format!("line {i}")].try_into().unwrap()]))
+                    .await;
+            }
+        })
+        .await?;
+        while frames.load(Ordering::SeqCst) < 3 {
+            tokio::task::yield_now().await;
+        }
+        driver.finish().await?;
+        output_task.wait().await;
+
+        let mut written = Vec::new();
+        reader.read_to_end(&mut written).await?;
+        assert!(frame_contains(&written, "working"));
+        assert!(frame_contains(&written, "done"));
+        for i in 0..10 {
+            assert!(frame_contains(&written, format!("line {i}")));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_async_driver_renders_while_flooded() -> anyhow::Result<()> {
+        let mut builder = Builder::new();
+        builder.custom_output(Box::new(
+            AsyncSuperConsoleOutput::new(
+                Box::new(tokio::io::sink()),
+                Box::new(tokio::io::sink()),
+                false,
+            )
+            .0,
+        ));
+        let console = builder.build_forced(Dimensions::new(80, 24))?;
+
+        let frames = Arc::new(AtomicUsize::new(0));
+        let driver = AsyncRenderDriver::spawn(console, 100, {
+            let frames = frames.clone();
+            move || {
+                frames.fetch_add(1, Ordering::SeqCst);
+                State("working")
+            }
+        });
+
+        // Keep the queue full for as long as the test runs.
+        let floods: Vec<_> = (0..3)
+            .map(|_| {
+                let sender = driver.emit_sender();
+                tokio::spawn(async move {
+                    loop {
+                        sender
+                            .emit(Lines(vec![vec!["line"].try_into().unwrap()]))
+                            .await;
+                    }
+                })
+            })
+            .collect();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while frames.load(Ordering::SeqCst) < 3 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await?;
+
+        for flood in floods {
+            flood.abort();
+        }
+        driver.finish().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_async_output_should_render() -> anyhow::Result<()> {
+        let (stream, mut reader) = tokio::io::duplex(1);
+        let (mut output, output_task) =
+            AsyncSuperConsoleOutput::new(Box::new(stream), Box::new(tokio::io::sink()), false);
+
+        // The writer task blocks on the first frame, the second one stays queued.
+        assert!(output.should_render());
+        output.output(b"ab".to_vec())?;
+        output.output(b"cd".to_vec())?;
+        assert!(!output.should_render());
+
+        Box::new(output).finalize()?;
+        let mut written = Vec::new();
+        let (read, ()) = tokio::join!(reader.read_to_end(&mut written), output_task.wait());
+        read?;
+        assert_eq!(written, b"abcd");
+
+        Ok(())
+    }
+}
@@ -8,24 +8,115 @@
  * above-listed licenses.
  */
 
+use std::cmp;
 use std::io;
 use std::io::Write;
+use std::time::Duration;
 
 use crate::Dimensions;
 use crate::SuperConsole;
+use crate::asciicast::AsciicastOutput;
 use crate::error::OutputError;
 use crate::output::BlockingSuperConsoleOutput;
 use crate::output::IsTtyWrite;
 use crate::output::NonBlockingSuperConsoleOutput;
+use crate::output::SizeProvider;
+use crate::output::SizedOutput;
 use crate::output::SuperConsoleOutput;
+use crate::restore::RestorableOutput;
+use crate::restore::Restore;
+use crate::style::ColorSupport;
+use crate::superconsole::SuperConsoleConfig;
+use crate::theme::Theme;
+
+/// When a console in plain mode prints the canvas. See [`Builder::build_or_plain`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanvasSnapshots {
+    /// Whenever the canvas differs from the last snapshot.
+    OnChange,
+    /// On the first render, then at most once per interval.
+    Every(Duration),
+    /// Only when the console is finalized.
+    Final,
+}
+
+impl Default for CanvasSnapshots {
+    fn default() -> Self {
+        Self::Every(Duration::from_secs(10))
+    }
+}
+
+/// The most rows the canvas may take. See [`Builder::max_canvas_height`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CanvasHeight {
+    /// A number of rows.
+    Rows(usize),
+    /// A fraction of the height available to the canvas, between 0 and 1, rounded down.
+    Fraction(f64),
+}
+
+impl CanvasHeight {
+    /// The number of rows out of `height`.
+    pub(crate) fn rows(self, height: usize) -> usize {
+        let rows = match self {
+            Self::Rows(rows) => rows,
+            Self::Fraction(fraction) => (height as f64 * fraction.clamp(0.0, 1.0)) as usize,
+        };
+        cmp::min(rows, height)
+    }
+}
+
+/// What to do with emitted lines once more are waiting to be drawn than
+/// [`Builder::max_buffered_graphemes`] allows. Until then, they are drawn a frame at a time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Stop draining [`Emitter`](crate::emitter::Emitter)s until the backlog is drawn, so that
+    /// producers are pushed back on once their queue is full.
+    ///
+    /// Only lines queued through emitters are held back: [`SuperConsole::emit`] and
+    /// [`SuperConsole::emit_aux`] add to the backlog directly. Producers only wait with
+    /// [`Builder::emitter_capacity`] bounding the queue, and when they emit with
+    /// [`Emitter::emit_blocking`](crate::emitter::Emitter::emit_blocking): `Emitter::emit` fails
+    /// with [`EmitError::Full`](crate::error::EmitError::Full) instead.
+    Block,
+    /// Draw the whole backlog at once, in a single frame.
+    #[default]
+    DumpAll,
+    /// Drop the oldest regular lines, keeping the most recent ones.
+    DropOldest,
+    /// Like `DropOldest`, and draw a "… N lines elided" line in place of the dropped lines, in the
+    /// [`dim`](crate::theme::Theme::dim) style of the theme.
+    Collapse,
+}
 
 /// A builder to create SuperConsole, with more options.
 pub struct Builder {
     non_blocking: bool,
     // The stream that superconsole writes to by default (emit output + canvas). By default is stderr.
-    stream: Box<dyn Write + Send + 'static + Sync>,
+    stream: Option<Box<dyn Write + Send + 'static + Sync>>,
     // The stream that superconsole writes to for auxiliary output. By default is stdout.
-    aux_stream: Box<dyn IsTtyWrite + Send + 'static + Sync>,
+    aux_stream: Option<Box<dyn IsTtyWrite + Send + 'static + Sync>>,
+    // An output replacing the streams above.
+    custom_output: Option<Box<dyn SuperConsoleOutput>>,
+    // Maximum number of bytes written at once by the output.
+    max_chunk_size: Option<usize>,
+    // Where to record the session as an asciicast, if anywhere.
+    recording: Option<Box<dyn Write + Send + Sync>>,
+    // Reports the terminal size in place of the output.
+    size_provider: Option<Box<dyn SizeProvider>>,
+    // The colors the terminal supports, detected from the environment if not set.
+    color_support: Option<ColorSupport>,
+    // When to print the canvas if the console falls back to plain mode.
+    snapshots: CanvasSnapshots,
+    // Whether to capture writes to stdout and stderr from other sources.
+    #[cfg(unix)]
+    capture_stdio: bool,
+    // Whether to restore the terminal when the process panics.
+    restore_on_panic: bool,
+    // Whether to restore the terminal when the process receives SIGINT or SIGTERM.
+    #[cfg(all(unix, feature = "signals"))]
+    restore_on_signals: bool,
+    config: SuperConsoleConfig,
 }
 
 impl Default for Builder {
@@ -38,8 +129,20 @@ impl Builder {
     pub fn new() -> Self {
         Self {
             non_blocking: false,
-            stream: Box::new(io::stderr()),
-            aux_stream: Box::new(io::stdout()),
+            stream: None,
+            aux_stream: None,
+            custom_output: None,
+            max_chunk_size: None,
+            recording: None,
+            size_provider: None,
+            color_support: None,
+            snapshots: CanvasSnapshots::default(),
+            #[cfg(unix)]
+            capture_stdio: false,
+            restore_on_panic: false,
+            #[cfg(all(unix, feature = "signals"))]
+            restore_on_signals: false,
+            config: SuperConsoleConfig::default(),
         }
     }
 
@@ -51,7 +154,164 @@ impl Builder {
 
     /// Write to a different I/O
     pub fn write_to(&mut self, stream: Box<dyn Write + Send + 'static + Sync>) -> &mut Self {
-        self.stream = stream;
+        self.stream = Some(stream);
+        self
+    }
+
+    /// Write auxillary output to a different I/O. It is written as is if `stream` is not a TTY.
+    pub fn aux_write_to(
+        &mut self,
+        stream: Box<dyn IsTtyWrite + Send + 'static + Sync>,
+    ) -> &mut Self {
+        self.aux_stream = Some(stream);
+        self
+    }
+
+    /// Take the size of the terminal from `provider`, rather than asking the output. Useful when
+    /// the streams lead to a terminal elsewhere, such as the other end of a pty.
+    pub fn size_provider(&mut self, provider: Box<dyn SizeProvider>) -> &mut Self {
+        self.size_provider = Some(provider);
+        self
+    }
+
+    /// Write through a custom output instead of the streams. Other options still apply.
+    pub fn custom_output(&mut self, output: Box<dyn SuperConsoleOutput>) -> &mut Self {
+        self.custom_output = Some(output);
+        self
+    }
+
+    /// Wrap each frame in synchronized update sequences, so that the terminal does not paint
+    /// partially drawn frames. Terminals without support ignore them. Enabled by default.
+    pub fn synchronized_output(&mut self, enabled: bool) -> &mut Self {
+        self.config.synchronized_output = enabled;
+        self
+    }
+
+    /// Hide the cursor while the canvas is displayed, showing it again when the console is
+    /// cleared, suspended, finalized or dropped. Enabled by default.
+    pub fn hide_cursor(&mut self, enabled: bool) -> &mut Self {
+        self.config.hide_cursor = enabled;
+        self
+    }
+
+    /// Bound the number of messages that [`Emitter`](crate::emitter::Emitter)s can queue between
+    /// two renders. Once full, emitting fails with [`EmitError::Full`](crate::error::EmitError::Full).
+    pub fn emitter_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.config.emitter_capacity = Some(capacity);
+        self
+    }
+
+    /// Draw the canvas on the alternate screen, giving the root component the whole terminal.
+    /// Emitted lines are held back and emitted once the primary screen is restored, which happens
+    /// when the console is finalized or dropped.
+    pub fn alternate_screen(&mut self) -> &mut Self {
+        self.config.alternate_screen = true;
+        self
+    }
+
+    /// The least number of emitted lines drawn per frame, even when the canvas leaves no room for
+    /// them, so that emitting doesn't stall on small terminals. 5 by default.
+    pub fn minimum_emit(&mut self, lines: usize) -> &mut Self {
+        self.config.minimum_emit = lines;
+        self
+    }
+
+    /// How many graphemes of emitted lines may wait to be drawn before the
+    /// [`overflow_policy`](Builder::overflow_policy) applies. 1,000,000 by default.
+    pub fn max_buffered_graphemes(&mut self, graphemes: usize) -> &mut Self {
+        self.config.max_buffered_graphemes = graphemes;
+        self
+    }
+
+    /// What to do once the emitted lines waiting to be drawn exceed
+    /// [`max_buffered_graphemes`](Builder::max_buffered_graphemes).
+    /// [`OverflowPolicy::DumpAll`] by default. Auxillary output is never dropped.
+    pub fn overflow_policy(&mut self, policy: OverflowPolicy) -> &mut Self {
+        self.config.overflow = policy;
+        self
+    }
+
+    /// Limit the height of the canvas, so that the rest of the terminal is left for emitted lines
+    /// to scroll past. The root component is drawn with the reduced dimensions. By default, the
+    /// canvas may take the whole terminal but the last row. Ignored on the alternate screen.
+    pub fn max_canvas_height(&mut self, height: CanvasHeight) -> &mut Self {
+        self.config.max_canvas_height = Some(height);
+        self
+    }
+
+    /// The colors the terminal supports, overriding [`ColorSupport::detect`]. Colors the terminal
+    /// does not support are mapped to the nearest supported one, or stripped with
+    /// [`ColorSupport::NoColor`].
+    pub fn color_support(&mut self, support: ColorSupport) -> &mut Self {
+        self.color_support = Some(support);
+        self
+    }
+
+    /// The theme current while components are drawn, see [`Theme::current`]. Plain by default.
+    pub fn theme(&mut self, theme: Theme) -> &mut Self {
+        self.config.theme = theme;
+        self
+    }
+
+    /// Hard-wrap emitted lines to the terminal width, keeping their styling, rather than leaving
+    /// long lines for the terminal to wrap.
+    pub fn wrap_emitted_lines(&mut self) -> &mut Self {
+        self.config.wrap_emitted_lines = true;
+        self
+    }
+
+    /// Split each frame into writes of at most `max_chunk_size` bytes, cutting only between escape
+    /// sequences and preferably at line ends. When the output is a pipe and this is at most
+    /// `PIPE_BUF`, a slow reader never sees half of an escape sequence. Ignored by custom outputs.
+    pub fn max_chunk_size(&mut self, max_chunk_size: usize) -> &mut Self {
+        self.max_chunk_size = Some(max_chunk_size);
+        self
+    }
+
+    /// Record everything written by the console to `writer`, as an asciicast v2 file that
+    /// asciinema can play. See [`AsciicastOutput`].
+    pub fn record_asciicast(&mut self, writer: Box<dyn Write + Send + Sync>) -> &mut Self {
+        self.recording = Some(writer);
+        self
+    }
+
+    /// When to print snapshots of the canvas in plain mode. Every 10 seconds by default.
+    pub fn canvas_snapshots(&mut self, snapshots: CanvasSnapshots) -> &mut Self {
+        self.snapshots = snapshots;
+        self
+    }
+
+    /// While the console is active, redirect the stdout and stderr file descriptors of the process
+    /// into pipes, and emit whatever other sources write to them above the canvas: stdout as
+    /// auxillary output, and stderr as regular output. The console itself writes to the original
+    /// streams, unless it was given other ones. They are restored when the console is finalized or
+    /// dropped.
+    #[cfg(unix)]
+    pub fn capture_stdio(&mut self) -> &mut Self {
+        self.capture_stdio = true;
+        self
+    }
+
+    /// Install a panic hook that, when the thread that last drew the console panics, clears the
+    /// canvas or leaves the alternate screen, restores stdout and stderr if captured, and waits a
+    /// little for the output to be flushed, all before the panic message is printed. The
+    /// components can't be drawn in [`DrawMode::Final`](crate::DrawMode::Final) then, as the panic
+    /// may have come from them. If the panic is caught, the console draws again below the panic
+    /// message on the next render. Panics on other threads leave the console alone.
+    ///
+    /// The hook is installed once per process, and calls whatever hook was installed before it.
+    pub fn restore_on_panic(&mut self) -> &mut Self {
+        self.restore_on_panic = true;
+        self
+    }
+
+    /// Handle SIGINT and SIGTERM by restoring the terminal like
+    /// [`restore_on_panic`](Builder::restore_on_panic) does, then terminating the process as the
+    /// signal would have by default. The handler stays installed once the console is finalized,
+    /// so don't use this if the program handles these signals itself.
+    #[cfg(all(unix, feature = "signals"))]
+    pub fn restore_on_signals(&mut self) -> &mut Self {
+        self.restore_on_signals = true;
         self
     }
 
@@ -63,27 +323,133 @@ impl Builder {
         Some(self.build_inner(None)).transpose()
     }
 
+    /// Build a new SuperConsole, in plain mode if stderr is not a TTY.
+    /// See [`build_plain`](Builder::build_plain).
+    pub fn build_or_plain(self, fallback_size: Dimensions) -> Result<SuperConsole, OutputError> {
+        if SuperConsole::compatible() {
+            self.build_inner(None)
+        } else {
+            self.build_plain(fallback_size)
+        }
+    }
+
+    /// Build a new SuperConsole in plain mode, which writes no escape codes. Emitted lines are
+    /// printed as plain text, and snapshots of the canvas are printed as set by
+    /// [`canvas_snapshots`](Builder::canvas_snapshots), and once more when finalizing.
+    pub fn build_plain(mut self, fallback_size: Dimensions) -> Result<SuperConsole, OutputError> {
+        self.config.plain = Some(self.snapshots);
+        self.config.alternate_screen = false;
+        self.config.wrap_emitted_lines = false;
+        self.build_inner(Some(fallback_size))
+    }
+
     /// Build a new SuperConsole regardless of whether stderr is a TTY.
     pub fn build_forced(self, fallback_size: Dimensions) -> Result<SuperConsole, OutputError> {
         self.build_inner(Some(fallback_size))
     }
 
+    #[cfg(unix)]
+    fn build_inner(
+        mut self,
+        fallback_size: Option<Dimensions>,
+    ) -> Result<SuperConsole, OutputError> {
+        use crate::capture::StdioCapture;
+
+        if !self.capture_stdio {
+            return self.build_without_capture(fallback_size);
+        }
+        let mut capture = StdioCapture::new()?;
+        let (stderr, stdout) = capture.streams()?;
+        self.stream.get_or_insert(Box::new(stderr));
+        self.aux_stream.get_or_insert(Box::new(stdout));
+        let mut console = self.build_without_capture(fallback_size)?;
+        if let Some(restore) = &console.restore {
+            restore.set_stdio(capture.saved()?);
+        }
+        capture.start(console.emitter())?;
+        console.capture = Some(capture);
+        Ok(console)
+    }
+
+    #[cfg(not(unix))]
     fn build_inner(self, fallback_size: Option<Dimensions>) -> Result<SuperConsole, OutputError> {
-        let output = self.output()?;
-        Ok(SuperConsole::new_with_output(fallback_size, output))
+        self.build_without_capture(fallback_size)
     }
 
-    fn output(self) -> Result<Box<dyn SuperConsoleOutput>, OutputError> {
+    fn build_without_capture(
+        self,
+        fallback_size: Option<Dimensions>,
+    ) -> Result<SuperConsole, OutputError> {
+        let config = SuperConsoleConfig {
+            color_support: self.color_support.unwrap_or_else(ColorSupport::detect),
+            ..self.config.clone()
+        };
+        let restore_on_panic = self.restore_on_panic;
+        #[cfg(all(unix, feature = "signals"))]
+        let restore_on_signals = self.restore_on_signals;
+        #[cfg(not(all(unix, feature = "signals")))]
+        let restore_on_signals = false;
+
+        let mut output = self.output(fallback_size)?;
+        if !(restore_on_panic || restore_on_signals) {
+            return Ok(SuperConsole::new_with_config(fallback_size, output, config));
+        }
+
+        let restore = Restore::new(output);
+        output = Box::new(RestorableOutput(restore.clone()));
+        if restore_on_panic {
+            Restore::on_panic(&restore);
+        }
+        #[cfg(all(unix, feature = "signals"))]
+        if restore_on_signals {
+            Restore::on_signals(&restore)?;
+        }
+        let mut console = SuperConsole::new_with_config(fallback_size, output, config);
+        console.restore = Some(restore);
+        Ok(console)
+    }
+
+    fn output(
+        mut self,
+        fallback_size: Option<Dimensions>,
+    ) -> Result<Box<dyn SuperConsoleOutput>, OutputError> {
+        let recording = self.recording.take();
+        let size_provider = self.size_provider.take();
+        let mut output = self.stream_output()?;
+        if let Some(size) = size_provider {
+            output = Box::new(SizedOutput {
+                inner: output,
+                size,
+            });
+        }
+        match recording {
+            Some(writer) => Ok(Box::new(AsciicastOutput::new(
+                output,
+                writer,
+                fallback_size,
+            )?)),
+            None => Ok(output),
+        }
+    }
+
+    fn stream_output(self) -> Result<Box<dyn SuperConsoleOutput>, OutputError> {
+        if let Some(output) = self.custom_output {
+            return Ok(output);
+        }
+        let stream = self.stream.unwrap_or_else(|| Box::new(io::stderr()));
+        let aux_stream = self.aux_stream.unwrap_or_else(|| Box::new(io::stdout()));
         if self.non_blocking {
             Ok(Box::new(NonBlockingSuperConsoleOutput::new(
-                self.stream,
-                self.aux_stream,
+                stream,
+                aux_stream,
+                self.max_chunk_size,
             )?))
         } else {
-            Ok(Box::new(BlockingSuperConsoleOutput::new(
-                self.stream,
-                self.aux_stream,
-            )))
+            let output = BlockingSuperConsoleOutput::new(stream, aux_stream);
+            Ok(Box::new(match self.max_chunk_size {
+                Some(max_chunk_size) => output.with_max_chunk_size(max_chunk_size),
+                None => output,
+            }))
         }
     }
 }
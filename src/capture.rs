@@ -0,0 +1,444 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Captures writes to the stdout and stderr file descriptors of the process, so that output from
+//! other sources is emitted above the canvas instead of corrupting it.
+
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::fd::BorrowedFd;
+use std::os::fd::FromRawFd;
+use std::os::fd::OwnedFd;
+use std::os::fd::RawFd;
+use std::time::Duration;
+
+use crossbeam_channel::Receiver;
+use crossbeam_channel::Sender;
+use crossbeam_channel::unbounded;
+
+use crate::Line;
+use crate::Lines;
+use crate::emitter::Emitter;
+use crate::error::OutputError;
+use crate::output::OutputTarget;
+
+/// How long to wait for the captured output to be read once the original fds are restored.
+/// The pipes stay open for as long as child processes hold on to them, so we can't wait forever.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Redirects stdout and stderr into pipes, read by threads that emit each line through an
+/// [`Emitter`]: stdout as auxillary output, and stderr as regular output.
+/// The console keeps writing to the original streams through the duplicates from
+/// [`streams`](StdioCapture::streams).
+pub(crate) struct StdioCapture {
+    saved: SavedStdio,
+    /// Receives a message from each reader thread as it exits. `None` until started.
+    readers: Option<Receiver<()>>,
+}
+
+/// Stdout and stderr pointed back at the original files, see [`StdioCapture::pause`].
+/// Dropping it without calling [`resume`](PausedCapture::resume) leaves them there.
+pub(crate) struct PausedCapture {
+    stdout_fd: RawFd,
+    stderr_fd: RawFd,
+    /// Duplicates of the write ends of the pipes, which also keep the readers running.
+    stdout_pipe: OwnedFd,
+    stderr_pipe: OwnedFd,
+}
+
+/// Duplicates of the original stdout and stderr, to point the standard fds back at.
+pub(crate) struct SavedStdio {
+    stdout_fd: RawFd,
+    stderr_fd: RawFd,
+    saved_stdout: OwnedFd,
+    saved_stderr: OwnedFd,
+}
+
+impl StdioCapture {
+    pub(crate) fn new() -> Result<Self, OutputError> {
+        Self::new_for_fds(libc::STDOUT_FILENO, libc::STDERR_FILENO)
+    }
+
+    fn new_for_fds(stdout_fd: RawFd, stderr_fd: RawFd) -> Result<Self, OutputError> {
+        let dup = |fd| {
+            // SAFETY: The fd is open for as long as the process runs, or the test.
+            unsafe { BorrowedFd::borrow_raw(fd) }
+                .try_clone_to_owned()
+                .map_err(OutputError::Capture)
+        };
+        Ok(Self {
+            saved: SavedStdio {
+                stdout_fd,
+                stderr_fd,
+                saved_stdout: dup(stdout_fd)?,
+                saved_stderr: dup(stderr_fd)?,
+            },
+            readers: None,
+        })
+    }
+
+    /// The original stderr and stdout, to be written to by the console.
+    pub(crate) fn streams(&self) -> Result<(File, File), OutputError> {
+        let saved = self.saved()?;
+        Ok((
+            File::from(saved.saved_stderr),
+            File::from(saved.saved_stdout),
+        ))
+    }
+
+    /// Another handle to the original stdout and stderr, to restore them from elsewhere.
+    pub(crate) fn saved(&self) -> Result<SavedStdio, OutputError> {
+        let saved = &self.saved;
+        Ok(SavedStdio {
+            stdout_fd: saved.stdout_fd,
+            stderr_fd: saved.stderr_fd,
+            saved_stdout: saved
+                .saved_stdout
+                .try_clone()
+                .map_err(OutputError::Capture)?,
+            saved_stderr: saved
+                .saved_stderr
+                .try_clone()
+                .map_err(OutputError::Capture)?,
+        })
+    }
+
+    /// Points stdout and stderr at new pipes, and starts emitting what is written to them.
+    /// On error, stdout and stderr are left pointing at the original files.
+    pub(crate) fn start(&mut self, emitter: Emitter) -> Result<(), OutputError> {
+        let (done, readers) = unbounded();
+        let started = [
+            (self.saved.stdout_fd, OutputTarget::Aux),
+            (self.saved.stderr_fd, OutputTarget::Main),
+        ]
+        .into_iter()
+        .try_for_each(|(fd, target)| {
+            let (read, write) = pipe().map_err(OutputError::Capture)?;
+            redirect(&write, fd).map_err(OutputError::Capture)?;
+
+            let emitter = emitter.clone();
+            let done = done.clone();
+            std::thread::Builder::new()
+                .name("superconsole-capture".to_owned())
+                .spawn(move || read_lines(read, emitter, target, done))
+                .map(drop)
+                .map_err(OutputError::SpawnThread)
+        });
+        if let Err(e) = started {
+            // A reader already started exits once its pipe is no longer the standard stream.
+            self.saved.restore();
+            return Err(e);
+        }
+        self.readers = Some(readers);
+        Ok(())
+    }
+
+    /// Points stdout and stderr back at the original files until the returned value is resumed.
+    pub(crate) fn pause(&self) -> Result<PausedCapture, OutputError> {
+        self.saved.pause()
+    }
+
+    /// Restores the original stdout and stderr, and waits a little for what was written to them
+    /// until now to be emitted.
+    pub(crate) fn stop(self) {
+        // Whatever Rust buffered must go to the pipes, not to the restored fds.
+        let _ignored = io::stdout().flush();
+
+        self.saved.restore();
+
+        if let Some(readers) = self.readers {
+            for _ in 0..2 {
+                if readers.recv_timeout(DRAIN_TIMEOUT).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl PausedCapture {
+    /// Points stdout and stderr at the pipes again.
+    pub(crate) fn resume(self) {
+        let _ignored = io::stdout().flush();
+        let _ignored = redirect(&self.stdout_pipe, self.stdout_fd);
+        let _ignored = redirect(&self.stderr_pipe, self.stderr_fd);
+    }
+}
+
+impl SavedStdio {
+    /// Points stdout and stderr back at the original files. Doing it twice is harmless.
+    pub(crate) fn restore(&self) {
+        let _ignored = redirect(&self.saved_stdout, self.stdout_fd);
+        let _ignored = redirect(&self.saved_stderr, self.stderr_fd);
+    }
+
+    /// Points stdout and stderr back at the original files, remembering what they pointed at to
+    /// resume from.
+    pub(crate) fn pause(&self) -> Result<PausedCapture, OutputError> {
+        // Whatever Rust buffered must go to the pipes, not to the restored fds.
+        let _ignored = io::stdout().flush();
+
+        let dup = |fd| {
+            // SAFETY: The fd is open for as long as the process runs, or the test.
+            unsafe { BorrowedFd::borrow_raw(fd) }
+                .try_clone_to_owned()
+                .map_err(OutputError::Capture)
+        };
+        let paused = PausedCapture {
+            stdout_fd: self.stdout_fd,
+            stderr_fd: self.stderr_fd,
+            stdout_pipe: dup(self.stdout_fd)?,
+            stderr_pipe: dup(self.stderr_fd)?,
+        };
+        self.restore();
+        Ok(paused)
+    }
+}
+
+fn pipe() -> io::Result<(File, OwnedFd)> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two fds written by `pipe`.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `pipe` succeeded, so we own both fds.
+    let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    // Child processes inherit `fd` once redirected, but never the pipe ends themselves.
+    for fd in [&read, &write] {
+        // SAFETY: Only sets a flag on an fd we own.
+        if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok((File::from(read), write))
+}
+
+/// Makes `fd` refer to the same file as `to`.
+fn redirect(to: &OwnedFd, fd: RawFd) -> io::Result<()> {
+    loop {
+        // SAFETY: Both fds are open, and `fd` is a standard stream we are allowed to replace.
+        if unsafe { libc::dup2(to.as_raw_fd(), fd) } != -1 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+fn read_lines(pipe: File, emitter: Emitter, target: OutputTarget, done: Sender<()>) {
+    let mut reader = BufReader::new(pipe);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let mut lines = Lines::from_colored_multiline_string(&String::from_utf8_lossy(&buffer));
+        if lines.is_empty() {
+            lines.push(Line::default());
+        }
+        // Wait for room rather than dropping lines, which holds back writers through the pipe.
+        let _ignored = match target {
+            OutputTarget::Main => emitter.emit_blocking(lines),
+            OutputTarget::Aux => emitter.emit_aux_blocking(lines),
+        };
+    }
+    let _ignored = done.send(());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+
+    use super::*;
+    use crate::Builder;
+    use crate::Dimensions;
+    use crate::SuperConsole;
+    use crate::components::echo::Echo;
+    use crate::superconsole::SuperConsoleConfig;
+    use crate::testing::SharedWriter;
+    use crate::testing::SuperConsoleTestingExt;
+    use crate::testing::TestOutput;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+    use crate::testing::test_console_with_config;
+
+    fn null() -> io::Result<File> {
+        OpenOptions::new().write(true).open("/dev/null")
+    }
+
+    /// Opens a new handle to whatever `fd` currently refers to.
+    fn reopen(fd: RawFd) -> io::Result<File> {
+        // SAFETY: The fd is kept open by the test.
+        Ok(File::from(
+            unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?,
+        ))
+    }
+
+    #[test]
+    fn test_capture() -> anyhow::Result<()> {
+        // Stand-ins for stdout and stderr, so that the test harness isn't affected.
+        let stdout = null()?;
+        let stderr = null()?;
+
+        let mut console = test_console();
+        let mut capture = StdioCapture::new_for_fds(stdout.as_raw_fd(), stderr.as_raw_fd())?;
+        capture.start(console.emitter())?;
+
+        let mut out = reopen(stdout.as_raw_fd())?;
+        writeln!(out, "stdout line")?;
+        writeln!(out)?;
+        let mut err = reopen(stderr.as_raw_fd())?;
+        writeln!(err, "\x1b[31mstderr line\x1b[0m")?;
+        write!(err, "partial")?;
+        drop((out, err));
+
+        capture.stop();
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+
+        let frames = &console.test_output().frames;
+        assert!(
+            frames.iter().any(|frame| frame_contains(
+                frame,
+                TestOutput::aux_output_with_prefix("stdout line")
+            ))
+        );
+        let frame = frames.last().unwrap();
+        assert!(frame_contains(frame, "stderr line"));
+        assert!(frame_contains(frame, "partial"));
+
+        // Writes now go to the original files again.
+        let mut out = reopen(stdout.as_raw_fd())?;
+        writeln!(out, "not captured")?;
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+        assert!(
+            !console
+                .test_output()
+                .frames
+                .iter()
+                .any(|frame| frame_contains(frame, "not captured"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_waits_for_room() -> anyhow::Result<()> {
+        let stdout = null()?;
+        let stderr = null()?;
+
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            emitter_capacity: Some(1),
+            ..Default::default()
+        });
+        let mut capture = StdioCapture::new_for_fds(stdout.as_raw_fd(), stderr.as_raw_fd())?;
+        capture.start(console.emitter())?;
+
+        // More lines than the queue holds, so the reader has to wait for renders.
+        let mut err = reopen(stderr.as_raw_fd())?;
+        for i in 0..5 {
+            writeln!(err, "line {i}")?;
+        }
+        drop(err);
+
+        let rendered = |console: &SuperConsole| {
+            console
+                .test_output()
+                .frames
+                .iter()
+                .any(|frame| frame_contains(frame, "line 4"))
+        };
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while !rendered(&console) && std::time::Instant::now() < deadline {
+            console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+        }
+        capture.stop();
+
+        for i in 0..5 {
+            assert!(
+                console
+                    .test_output()
+                    .frames
+                    .iter()
+                    .any(|frame| frame_contains(frame, format!("line {i}")))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_suspend_points_stdio_back() -> anyhow::Result<()> {
+        // A pipe stands in for the terminal, so that what reaches it can be read back.
+        let (mut terminal, stdout) = pipe()?;
+        let stderr = null()?;
+
+        let mut console = test_console();
+        let mut capture = StdioCapture::new_for_fds(stdout.as_raw_fd(), stderr.as_raw_fd())?;
+        capture.start(console.emitter())?;
+        console.capture = Some(capture);
+
+        let guard = console.suspend()?;
+        let mut out = reopen(stdout.as_raw_fd())?;
+        writeln!(out, "while suspended")?;
+        drop(out);
+        let mut read = [0; 64];
+        let len = io::Read::read(&mut terminal, &mut read)?;
+        assert_eq!(&read[..len], b"while suspended\n");
+
+        // Captured again once resumed.
+        guard.resume();
+        let mut out = reopen(stdout.as_raw_fd())?;
+        writeln!(out, "resumed")?;
+        drop(out);
+        console.capture.take().unwrap().stop();
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+        assert!(
+            console
+                .test_output()
+                .frames
+                .iter()
+                .any(|frame| frame_contains(frame, "resumed"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_emits_captured_lines() -> anyhow::Result<()> {
+        let stdout = null()?;
+        let stderr = null()?;
+
+        let writer = SharedWriter::default();
+        let mut builder = Builder::new();
+        builder.write_to(Box::new(writer.clone()));
+        let mut console = builder.build_forced(Dimensions::new(80, 24))?;
+        let mut capture = StdioCapture::new_for_fds(stdout.as_raw_fd(), stderr.as_raw_fd())?;
+        capture.start(console.emitter())?;
+        console.capture = Some(capture);
+
+        let mut err = reopen(stderr.as_raw_fd())?;
+        writeln!(err, "captured before drop")?;
+        drop(err);
+        drop(console);
+
+        assert!(frame_contains(
+            &writer.0.lock().unwrap(),
+            "captured before drop"
+        ));
+        Ok(())
+    }
+}
@@ -19,6 +19,8 @@ use crate::Span;
 use crate::components::Aligned;
 use crate::components::alignment::HorizontalAlignmentKind;
 use crate::components::alignment::VerticalAlignmentKind;
+use crate::style::ContentStyle;
+use crate::theme::Theme;
 
 /// The `Bordered` component can be used to put borders on all sides of the output of its child.
 /// This is useful for delimiting the boundaries of a component for reading and aesthetic purposes.
@@ -43,6 +45,7 @@ use crate::components::alignment::VerticalAlignmentKind;
 pub struct Bordered<C: Component> {
     child: Aligned<C>,
     pub border: BorderedSpec,
+    style: Option<ContentStyle>,
 }
 
 /// The `BorderedSpec` allows the callee to specify the borders (or lack thereof) of each side.
@@ -80,8 +83,17 @@ impl<C: Component> Bordered<C> {
                 vertical: VerticalAlignmentKind::Top,
             },
             border,
+            style: None,
         }
     }
+
+    /// Draws every border with `style`, in place of the style of its span. Otherwise, each border
+    /// takes the [`border`](Theme::border) style of the current theme, with the colors and
+    /// attributes of its span on top.
+    pub fn with_style(mut self, style: ContentStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
 }
 
 /// helper method to transpose horizontal padding.
@@ -120,20 +132,43 @@ impl<C: Component> Component for Bordered<C> {
         // The [`Aligned`] box ensures that the child is justified and bounded.
         let mut output = self.child.draw(new_dims, mode)?;
 
+        let theme = Theme::current();
+        let themed = |border: &Span| {
+            let mut border = border.clone();
+            border.style = match self.style {
+                Some(style) => style,
+                None => ContentStyle {
+                    foreground_color: border
+                        .style
+                        .foreground_color
+                        .or(theme.border.foreground_color),
+                    background_color: border
+                        .style
+                        .background_color
+                        .or(theme.border.background_color),
+                    underline_color: border
+                        .style
+                        .underline_color
+                        .or(theme.border.underline_color),
+                    attributes: border.style.attributes | theme.border.attributes,
+                },
+            };
+            border
+        };
         for line in output.iter_mut() {
             if let Some(left) = &self.border.left {
-                line.push_front(left.clone());
+                line.push_front(themed(left));
             }
             if let Some(right) = &self.border.right {
-                line.push(right.clone());
+                line.push(themed(right));
             }
         }
         if let Some(top) = &self.border.top {
-            let lines = construct_vertical_padding(top.clone(), output.max_line_length());
+            let lines = construct_vertical_padding(themed(top), output.max_line_length());
             output.0.splice(0..0, lines);
         }
         if let Some(bottom) = &self.border.bottom {
-            let lines = construct_vertical_padding(bottom.clone(), output.max_line_length());
+            let lines = construct_vertical_padding(themed(bottom), output.max_line_length());
             output.extend(lines);
         }
 
@@ -147,6 +182,8 @@ mod tests {
 
     use super::*;
     use crate::components::echo::Echo;
+    use crate::style::Color;
+    use crate::style::Stylize;
 
     #[derive(AsRef, Debug)]
     #[allow(dead_code)]
@@ -238,4 +275,45 @@ mod tests {
         assert_eq!(output, expected);
         Ok(())
     }
+
+    #[test]
+    fn test_theme() -> anyhow::Result<()> {
+        let component = Bordered::new(
+            Echo(Lines(vec![vec!["Test"].try_into()?])),
+            BorderedSpec {
+                right: Some(Span::new_styled("#".to_owned().bold())?),
+                ..Default::default()
+            },
+        );
+        let output =
+            Theme::dark().scope(|| component.draw(Dimensions::new(6, 3), DrawMode::Normal))?;
+
+        let expected = Lines(vec![
+            Line::from_iter([Span::new_styled("-".repeat(6).with(Color::DarkGrey))?]),
+            Line::from_iter([
+                Span::new_styled("|".to_owned().with(Color::DarkGrey))?,
+                Span::new_unstyled("Test")?,
+                Span::new_styled("#".to_owned().with(Color::DarkGrey).bold())?,
+            ]),
+            Line::from_iter([Span::new_styled("-".repeat(6).with(Color::DarkGrey))?]),
+        ]);
+        assert_eq!(output, expected);
+
+        // An explicit style wins over the theme, even when it is the default style.
+        let component = Bordered::new(
+            Echo(Lines(vec![vec!["Test"].try_into()?])),
+            BorderedSpec::default(),
+        )
+        .with_style(ContentStyle::default());
+        let output =
+            Theme::dark().scope(|| component.draw(Dimensions::new(6, 3), DrawMode::Normal))?;
+        let expected = Lines(vec![
+            vec!["-".repeat(6)].try_into()?,
+            vec!["|", "Test", "|"].try_into()?,
+            vec!["-".repeat(6)].try_into()?,
+        ]);
+        assert_eq!(output, expected);
+
+        Ok(())
+    }
 }
@@ -18,6 +18,7 @@ use crate::Lines;
 use crate::Span;
 use crate::components::Component;
 use crate::components::DrawMode;
+use crate::theme::Theme;
 
 /// Default braille spinner characters used for animation.
 pub const BRAILLE_SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
@@ -25,7 +26,8 @@ pub const BRAILLE_SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴',
 /// A component that renders a spinner with a message.
 ///
 /// The spinner animates through characters based on the `tick` value,
-/// and displays a message `Line` alongside it.
+/// and displays a message `Line` alongside it. The character takes the
+/// [`spinner`](Theme::spinner) style of the current theme.
 ///
 /// # Example
 /// ```ignore
@@ -83,8 +85,9 @@ impl Component for Spinner {
             }
             DrawMode::Normal => {
                 // Prepend spinner character to the message
-                let spinner_span = Span::sanitized(format!("{} ", self.current_char()));
-                let mut line = Line::from_iter([spinner_span]);
+                let mut spinner_span = Span::sanitized(self.current_char());
+                spinner_span.style = Theme::current().spinner;
+                let mut line = Line::from_iter([spinner_span, Span::padding(1)]);
                 line.extend(self.message.clone());
                 Ok(Lines(vec![line]))
             }
@@ -95,6 +98,8 @@ impl Component for Spinner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::style::Color;
+    use crate::style::Stylize;
 
     #[test]
     fn test_spinner_cycles() {
@@ -144,4 +149,22 @@ mod tests {
         let spinner = Spinner::with_chars(1, message, chars);
         assert_eq!(spinner.current_char(), '/');
     }
+
+    #[test]
+    fn test_theme() -> anyhow::Result<()> {
+        let spinner = Spinner::new(0, Line::sanitized("Loading..."));
+
+        let plain = spinner.draw_unchecked(Dimensions::new(80, 24), DrawMode::Normal)?;
+        assert_eq!(plain, Lines(vec![Line::sanitized("⠋ Loading...")]));
+
+        let themed = Theme::dark()
+            .scope(|| spinner.draw_unchecked(Dimensions::new(80, 24), DrawMode::Normal))?;
+        let expected = Line::from_iter([
+            Span::new_styled("⠋".to_owned().with(Color::Cyan))?,
+            Span::new_unstyled(" Loading...")?,
+        ]);
+        assert_eq!(themed, Lines(vec![expected]));
+
+        Ok(())
+    }
 }
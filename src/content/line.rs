@@ -8,6 +8,8 @@
  * above-listed licenses.
  */
 
+use std::borrow::Cow;
+use std::cmp;
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Display;
@@ -21,10 +23,12 @@ use crossterm::Command;
 use crossterm::cursor::MoveToColumn;
 use crossterm::terminal::Clear;
 use crossterm::terminal::ClearType;
+use termwiz::cell;
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::Span;
 use crate::SpanError;
+use crate::style::ColorSupport;
 use crate::vec_as_fmt_write::VecAsFmtWrite;
 
 /// A `Line` is an abstraction for a collection of stylized or unstylized strings.
@@ -37,6 +41,29 @@ pub struct Line(
     Vec<Span>,
 );
 
+/// The part of a [`Line`] that differs from the line previously rendered on the same row.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct LineChange {
+    /// Column of the first cell that changed.
+    pub(crate) column: usize,
+    /// The cells to write starting at `column`.
+    pub(crate) content: Line,
+    /// Whether the remainder of the row must be cleared after writing `content`.
+    pub(crate) clear: bool,
+}
+
+impl LineChange {
+    /// Writes the changed cells, assuming the cursor is already at `column`.
+    pub(crate) fn render(&self, writer: &mut Vec<u8>) {
+        self.content.render_spans(writer);
+        if self.clear {
+            Clear(ClearType::UntilNewLine)
+                .write_ansi(&mut VecAsFmtWrite(writer))
+                .unwrap();
+        }
+    }
+}
+
 impl Line {
     pub fn unstyled(text: &str) -> Result<Line, SpanError> {
         Ok(Line::from_iter([Span::new_unstyled(text)?]))
@@ -150,15 +177,48 @@ impl Line {
         }
     }
 
+    /// The number of terminal rows the line takes up once the terminal wraps it at `width` columns.
+    /// An empty line still takes up a row.
+    pub fn rows(&self, width: usize) -> usize {
+        if width == 0 {
+            return 1;
+        }
+        cmp::max(1, self.len().div_ceil(width))
+    }
+
+    /// Splits the line into lines no wider than `width`, keeping the styling of each span.
+    /// A grapheme wider than the space left on a row moves to the next row.
+    pub fn wrap(&self, width: usize) -> Vec<Line> {
+        if self.len() <= width || width == 0 {
+            return vec![self.clone()];
+        }
+
+        let mut lines = Vec::new();
+        let mut current = Line::default();
+        let mut current_width = 0;
+        for (span, grapheme) in self.cells() {
+            let grapheme_width = cell::unicode_column_width(grapheme, None);
+            if current_width + grapheme_width > width && current_width > 0 {
+                lines.push(mem::take(&mut current));
+                current_width = 0;
+            }
+            current.push(Span {
+                content: Cow::Owned(grapheme.to_owned()),
+                style: span.style,
+                hyperlink: span.hyperlink.clone(),
+            });
+            current_width += grapheme_width;
+        }
+        lines.push(current);
+        lines
+    }
+
     /// Renders the formatted content of the line to `stdout`.
     /// The buffer must be flushed to produce output.
     pub(crate) fn render_with_clear_and_nl(&self, writer: &mut Vec<u8>) {
-        let mut writer = VecAsFmtWrite(writer);
-
-        for word in &self.0 {
-            word.render(&mut writer).unwrap();
-        }
+        self.render_spans(writer);
 
+        let mut writer = VecAsFmtWrite(writer);
         Clear(ClearType::UntilNewLine)
             .write_ansi(&mut writer)
             .unwrap();
@@ -166,6 +226,75 @@ impl Line {
         MoveToColumn(0).write_ansi(&mut writer).unwrap();
     }
 
+    /// Renders only the spans of the line, without clearing the rest of the row or moving the cursor.
+    pub(crate) fn render_spans(&self, writer: &mut Vec<u8>) {
+        let mut writer = VecAsFmtWrite(writer);
+        for word in &self.0 {
+            word.render(&mut writer).unwrap();
+        }
+    }
+
+    /// Iterates over the graphemes of the line, along with the span each one belongs to.
+    fn cells(&self) -> impl DoubleEndedIterator<Item = (&Span, &str)> {
+        self.0
+            .iter()
+            .flat_map(|span| span.content.graphemes(true).map(move |g| (span, g)))
+    }
+
+    /// Compares this line to the `previous` line rendered on the same row, cell by cell.
+    /// Returns `None` if nothing changed, otherwise the smallest run of cells that must be rewritten.
+    ///
+    /// If both lines have the same width, unchanged cells at the end of the line are kept too.
+    pub(crate) fn changes_from(&self, previous: &Line) -> Option<LineChange> {
+        fn same_cell((s1, g1): &(&Span, &str), (s2, g2): &(&Span, &str)) -> bool {
+            g1 == g2 && s1.is_mergeable_with(s2)
+        }
+
+        let new: Vec<_> = self.cells().collect();
+        let old: Vec<_> = previous.cells().collect();
+
+        let prefix = new
+            .iter()
+            .zip(old.iter())
+            .take_while(|(n, o)| same_cell(n, o))
+            .count();
+        if prefix == new.len() && prefix == old.len() {
+            return None;
+        }
+        let column = new[..prefix]
+            .iter()
+            .map(|(_, g)| cell::unicode_column_width(g, None))
+            .sum();
+
+        // Cells after the change can only be kept if they stay in the same columns.
+        let (suffix, clear) = if self.len() == previous.len() {
+            let suffix = new[prefix..]
+                .iter()
+                .rev()
+                .zip(old[prefix..].iter().rev())
+                .take_while(|(n, o)| same_cell(n, o))
+                .count();
+            (suffix, false)
+        } else {
+            (0, true)
+        };
+
+        let content = new[prefix..new.len() - suffix]
+            .iter()
+            .map(|(span, g)| Span {
+                content: Cow::Owned((*g).to_owned()),
+                style: span.style,
+                hyperlink: span.hyperlink.clone(),
+            })
+            .collect();
+
+        Some(LineChange {
+            column,
+            content,
+            clear,
+        })
+    }
+
     /// Render the line as a string with ANSI escape codes.
     ///
     /// Without trailing newline or an escape sequence to clear the line.
@@ -193,15 +322,27 @@ impl Line {
         if span.is_empty() {
             return;
         }
-        if let Some(last) = self.0.last_mut() {
-            if last.is_mergeable_with(&span) {
-                last.content.to_mut().push_str(&span.content);
-                return;
-            }
+        if let Some(last) = self.0.last_mut()
+            && last.is_mergeable_with(&span)
+        {
+            last.content.to_mut().push_str(&span.content);
+            return;
         }
         self.0.push(span);
     }
 
+    /// Maps the colors of the line to those the terminal supports, merging spans whose styles
+    /// became equal.
+    pub(crate) fn downgrade_colors(&mut self, support: ColorSupport) {
+        *self = mem::take(&mut self.0)
+            .into_iter()
+            .map(|mut span| {
+                span.downgrade_colors(support);
+                span
+            })
+            .collect();
+    }
+
     /// Prepend a span to the line.
     pub fn push_front(&mut self, span: Span) {
         let this = mem::take(self);
@@ -290,6 +431,22 @@ mod tests {
         assert_eq!(Line::default().len(), 0);
     }
 
+    #[test]
+    fn test_downgrade_colors_merges_spans() {
+        let red = |r| Color::Rgb { r, g: 0, b: 0 };
+        let mut line = Line::from_iter([
+            Span::new_colored("dark", red(250)).unwrap(),
+            Span::new_colored("er", red(255)).unwrap(),
+        ]);
+        assert_eq!(line.0.len(), 2);
+
+        line.downgrade_colors(ColorSupport::Ansi16);
+        assert_eq!(
+            line,
+            Line::from_iter([Span::new_colored("darker", Color::Red).unwrap()])
+        );
+    }
+
     #[test]
     fn test_pad_line_right() {
         let mut test = Line::from_iter([
@@ -409,6 +566,117 @@ mod tests {
         assert_eq!(expected, line);
     }
 
+    #[test]
+    fn test_changes_from() {
+        let line = |spans: &[&str]| -> Line { spans.to_vec().try_into().unwrap() };
+
+        assert_eq!(line(&["hello"]).changes_from(&line(&["hel", "lo"])), None);
+
+        // Same width: only the middle is rewritten.
+        assert_eq!(
+            line(&["\u{2819} Building 2/40"]).changes_from(&line(&["\u{280b} Building 1/40"])),
+            Some(LineChange {
+                column: 0,
+                content: line(&["\u{2819} Building 2"]),
+                clear: false,
+            })
+        );
+        assert_eq!(
+            line(&["abcXef"]).changes_from(&line(&["abcdef"])),
+            Some(LineChange {
+                column: 3,
+                content: line(&["X"]),
+                clear: false,
+            })
+        );
+
+        // Different width: the rest of the row is rewritten and cleared.
+        assert_eq!(
+            line(&["abc"]).changes_from(&line(&["abcdef"])),
+            Some(LineChange {
+                column: 3,
+                content: Line::default(),
+                clear: true,
+            })
+        );
+        assert_eq!(
+            line(&["abcdefgh"]).changes_from(&line(&["abXdef"])),
+            Some(LineChange {
+                column: 2,
+                content: line(&["cdefgh"]),
+                clear: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_changes_from_style() {
+        let plain = Line::from_iter([Span::new_unstyled("ab").unwrap()]);
+        let styled = Line::from_iter([
+            Span::new_unstyled("a").unwrap(),
+            Span::new_colored("b", Color::Red).unwrap(),
+        ]);
+
+        assert_eq!(
+            styled.changes_from(&plain),
+            Some(LineChange {
+                column: 1,
+                content: Line::from_iter([Span::new_colored("b", Color::Red).unwrap()]),
+                clear: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_changes_from_wide_chars() {
+        let line = |spans: &[&str]| -> Line { spans.to_vec().try_into().unwrap() };
+
+        assert_eq!(
+            line(&["\u{1f9b6}\u{1f9b6}b"]).changes_from(&line(&["\u{1f9b6}\u{1f9b6}a"])),
+            Some(LineChange {
+                column: 4,
+                content: line(&["b"]),
+                clear: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_wrap() {
+        let line = Line::from_iter([
+            Span::new_colored("abra", Color::Blue).unwrap(),
+            Span::new_colored("cadabra", Color::Red).unwrap(),
+        ]);
+
+        let wrapped = line.wrap(5);
+        assert_eq!(
+            wrapped,
+            vec![
+                Line::from_iter([
+                    Span::new_colored("abra", Color::Blue).unwrap(),
+                    Span::new_colored("c", Color::Red).unwrap(),
+                ]),
+                Line::from_iter([Span::new_colored("adabr", Color::Red).unwrap()]),
+                Line::from_iter([Span::new_colored("a", Color::Red).unwrap()]),
+            ]
+        );
+        assert_eq!(line.rows(5), 3);
+        assert_eq!(line.wrap(11), vec![line.clone()]);
+        assert_eq!(line.rows(11), 1);
+        assert_eq!(Line::default().rows(5), 1);
+
+        // A wide grapheme is not split across rows.
+        let line: Line = vec!["a\u{1f9b6}b"].try_into().unwrap();
+        assert_eq!(
+            line.wrap(2),
+            vec![
+                vec!["a"].try_into().unwrap(),
+                vec!["\u{1f9b6}"].try_into().unwrap(),
+                vec!["b"].try_into().unwrap(),
+            ]
+        );
+    }
+
     #[test]
     fn test_fmt_for_test() {
         let line = Line::from_iter([
@@ -17,9 +17,16 @@ use std::io::Write;
 use std::iter;
 use std::mem;
 
+use crossterm::QueueableCommand;
+use crossterm::cursor::MoveDown;
+use crossterm::cursor::MoveTo;
+use crossterm::cursor::MoveToColumn;
+use crossterm::cursor::MoveUp;
 use crossterm::style::Attribute;
 use crossterm::style::Attributes;
 use crossterm::style::Color;
+use crossterm::terminal::Clear;
+use crossterm::terminal::ClearType;
 use itertools::Itertools;
 use termwiz::cell::Hyperlink;
 use termwiz::cell::Intensity;
@@ -33,6 +40,8 @@ use termwiz::escape::csi::Sgr;
 use crate::Dimensions;
 use crate::Line;
 use crate::Span;
+use crate::content::line::LineChange;
+use crate::style::ColorSupport;
 use crate::style::ContentStyle;
 use crate::style::StyledContent;
 
@@ -110,13 +119,12 @@ impl ColoredStringParser {
                 self.push_current();
                 self.background_color = Self::spec_to_color(spec);
             }
-            Action::OperatingSystemCommand(cmd) => match *cmd {
-                OperatingSystemCommand::SetHyperlink(hy) => {
+            Action::OperatingSystemCommand(cmd) => {
+                if let OperatingSystemCommand::SetHyperlink(hy) = *cmd {
                     self.push_current();
                     self.hyperlink = hy;
                 }
-                _ => {}
-            },
+            }
             _ => {}
         });
         self.push_current();
@@ -297,7 +305,9 @@ impl Lines {
 
     /// Like `render`, but with a limit.
     /// If a limit is specified, no more than that amount will be drained.
-    /// The limit is on the number of *lines*, **NOT** the number of *bytes*.
+    /// The limit is on the number of terminal *rows* the lines take up at `width` columns,
+    /// **NOT** the number of *bytes*. A line wider than the remaining limit is still drained if it
+    /// is the first one, so that lines taller than the limit don't block the buffer forever.
     /// Care should be taken with calling a limit of 0 - this will cause no lines to render and the buffer to never be drained.
     ///
     /// Returns the remain limit after rendering.  If the limit is None, means no limit
@@ -305,24 +315,46 @@ impl Lines {
         &mut self,
         writer: &mut Vec<u8>,
         limit: Option<usize>,
+        width: usize,
     ) -> Option<usize> {
-        let output_limit = limit.unwrap_or(self.len());
-        let amt = cmp::min(output_limit, self.len());
+        let Some(mut remaining) = limit else {
+            // if the original limit was None, it means no limit, so just return None meaning no limit
+            for line in self.0.drain(..) {
+                line.render_with_clear_and_nl(writer);
+            }
+            return None;
+        };
+
+        let mut amt = 0;
+        for line in &self.0 {
+            let rows = line.rows(width);
+            if remaining == 0 || (rows > remaining && amt > 0) {
+                break;
+            }
+            remaining = remaining.saturating_sub(rows);
+            amt += 1;
+        }
         for line in self.0.drain(..amt) {
             line.render_with_clear_and_nl(writer);
         }
-        if limit.is_some() {
-            Some(output_limit - amt)
-        } else {
-            // if the original limit was None, it means no limit, so just return None meaning no limit
-            None
+        Some(remaining)
+    }
+
+    /// Hard-wraps every line to at most `width` columns. See [`Line::wrap`].
+    pub fn wrap(&mut self, width: usize) {
+        if self.iter().all(|line| line.len() <= width) {
+            return;
         }
+        self.0 = mem::take(&mut self.0)
+            .into_iter()
+            .flat_map(|line| line.wrap(width))
+            .collect();
     }
 
     /// Formats and renders all lines to `buffer`.
     /// Notably, this *queues* the lines for rendering.  You must flush the buffer.
-    pub(crate) fn render_from_line(&self, writer: &mut Vec<u8>, start: usize) {
-        for line in self.0.iter().skip(start) {
+    pub(crate) fn render(&self, writer: &mut Vec<u8>) {
+        for line in self.0.iter() {
             line.render_with_clear_and_nl(writer);
         }
     }
@@ -336,12 +368,61 @@ impl Lines {
         self.0.clear();
     }
 
-    pub(crate) fn lines_equal(&self, other: &Self) -> usize {
-        self.0
-            .iter()
-            .zip(other.0.iter())
-            .take_while(|(l1, l2)| l1 == l2)
-            .count()
+    /// Renders only the cells that differ from the `previous` lines, which are assumed to be on screen
+    /// with the cursor at the start of the row just below them.
+    /// Afterwards, the cursor is at the start of the row just below these lines.
+    /// Rows left over from `previous` are not cleared.
+    pub(crate) fn render_diff(&self, writer: &mut Vec<u8>, previous: &Lines) {
+        let mut cursor = CursorRow {
+            row: previous.len(),
+            existing: previous.len(),
+        };
+
+        for (row, change) in self.changes_from(previous) {
+            cursor.move_to(writer, row);
+            writer.queue(MoveToColumn(change.column as u16)).unwrap();
+            change.render(writer);
+        }
+
+        cursor.move_to(writer, self.len());
+        writer.queue(MoveToColumn(0)).unwrap();
+    }
+
+    /// Like `render_diff`, but positions the cursor absolutely, with these lines starting at the top
+    /// left of the screen. The cursor never moves past the last row, so the screen doesn't scroll.
+    /// Rows left over from `previous` are cleared.
+    pub(crate) fn render_diff_absolute(&self, writer: &mut Vec<u8>, previous: &Lines) {
+        for (row, change) in self.changes_from(previous) {
+            writer
+                .queue(MoveTo(change.column as u16, row as u16))
+                .unwrap();
+            change.render(writer);
+        }
+
+        if previous.len() > self.len() {
+            writer.queue(MoveTo(0, self.len() as u16)).unwrap();
+            writer.queue(Clear(ClearType::FromCursorDown)).unwrap();
+        }
+    }
+
+    /// The changes needed to turn the `previous` lines into these, by row.
+    fn changes_from<'a>(
+        &'a self,
+        previous: &'a Lines,
+    ) -> impl Iterator<Item = (usize, LineChange)> + 'a {
+        self.iter()
+            .enumerate()
+            .filter_map(|(row, line)| match previous.0.get(row) {
+                Some(previous_line) => line.changes_from(previous_line).map(|c| (row, c)),
+                None => Some((
+                    row,
+                    LineChange {
+                        column: 0,
+                        content: line.clone(),
+                        clear: true,
+                    },
+                )),
+            })
     }
 
     /// Returns the maximum line width and the number of lines.
@@ -387,6 +468,15 @@ impl Lines {
             .unwrap()
     }
 
+    /// Maps the colors of every line to those the terminal supports.
+    pub(crate) fn downgrade_colors(&mut self, support: ColorSupport) {
+        if support == ColorSupport::TrueColor {
+            return;
+        }
+        self.iter_mut()
+            .for_each(|line| line.downgrade_colors(support));
+    }
+
     pub fn fmt_for_test(&self) -> impl Display + '_ {
         struct Impl<'a>(&'a Lines);
         impl Display for Impl<'_> {
@@ -401,6 +491,37 @@ impl Lines {
     }
 }
 
+/// Tracks the row of the cursor while rendering a diff, relative to the top of the canvas.
+struct CursorRow {
+    row: usize,
+    /// Rows up to and including this one are already on screen. Moving further down requires
+    /// newlines, so that the terminal scrolls if needed.
+    existing: usize,
+}
+
+impl CursorRow {
+    fn move_to(&mut self, writer: &mut Vec<u8>, row: usize) {
+        while self.row > row {
+            // We can only move at most u16 at a time, so repeat until we move enough
+            let step = (self.row - row).try_into().unwrap_or(u16::MAX);
+            writer.queue(MoveUp(step)).unwrap();
+            self.row -= step as usize;
+        }
+        while self.row < row.min(self.existing) {
+            let step = (row.min(self.existing) - self.row)
+                .try_into()
+                .unwrap_or(u16::MAX);
+            writer.queue(MoveDown(step)).unwrap();
+            self.row += step as usize;
+        }
+        while self.row < row {
+            writer.push(b'\n');
+            self.row += 1;
+        }
+        self.existing = cmp::max(self.existing, self.row);
+    }
+}
+
 impl FromIterator<Line> for Lines {
     fn from_iter<I: IntoIterator<Item = Line>>(iter: I) -> Self {
         Self(iter.into_iter().collect())
@@ -27,6 +27,8 @@ use termwiz::cell::Hyperlink;
 use unicode_segmentation::Graphemes;
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::style::ColorSupport;
+
 #[derive(Debug, thiserror::Error)]
 pub enum SpanError {
     #[error("Word {0} contains non-space whitespace")]
@@ -95,6 +97,18 @@ impl Span {
         }
     }
 
+    /// Maps the colors of the span to those the terminal supports.
+    pub(crate) fn downgrade_colors(&mut self, support: ColorSupport) {
+        let style = &mut self.style;
+        for color in [
+            &mut style.foreground_color,
+            &mut style.background_color,
+            &mut style.underline_color,
+        ] {
+            *color = color.and_then(|color| support.downgrade(color));
+        }
+    }
+
     /// Determine if this span is mergeable with another span, i.e. if they
     /// are equal except for content.
     pub fn is_mergeable_with(&self, other: &Span) -> bool {
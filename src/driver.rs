@@ -0,0 +1,320 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! A driver that renders a [`SuperConsole`] on a dedicated thread at a fixed frame rate,
+//! so that callers don't have to write their own render loop.
+
+use std::cmp;
+use std::io;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+use crossbeam_channel::RecvTimeoutError;
+use crossbeam_channel::Sender;
+use crossbeam_channel::bounded;
+use crossbeam_channel::unbounded;
+
+use crate::Lines;
+use crate::SuperConsole;
+use crate::SuspendGuard;
+use crate::components::Component;
+use crate::emitter::Emitter;
+use crate::error::EmitError;
+use crate::error::Error;
+use crate::error::OutputError;
+
+/// Requests to the render loop.
+enum Control {
+    /// Render now, unless a requested render already happened within the last interval.
+    Render,
+    /// Suspend the console, and send back the guard.
+    Suspend(Sender<Result<SuspendGuard, OutputError>>),
+    /// Finalize the console and exit.
+    Finish,
+}
+
+/// Owns a [`SuperConsole`] on a dedicated thread, and renders it at a fixed frame rate.
+///
+/// At each frame, the component factory is called to produce the root component, typically from
+/// a snapshot of some shared state. Lines are emitted from any thread through the console's
+/// [`Emitter`]; everything emitted between two frames is drained by a single render.
+/// [`request_render`](RenderDriver::request_render) renders ahead of the next frame, for instance
+/// right after the state changed.
+/// Call [`finish`](RenderDriver::finish) to perform the final render and wait for the output to
+/// be flushed.
+pub struct RenderDriver<E> {
+    emitter: Emitter,
+    /// Sends requests to the render loop. Dropping it stops the loop too.
+    control: Option<Sender<Control>>,
+    thread: Option<JoinHandle<Result<(), Error<E>>>>,
+}
+
+impl<E: Send + 'static> RenderDriver<E> {
+    /// Moves the console to a new thread and starts rendering `frames_per_second` times per second.
+    /// A rate of 0 is treated as 1.
+    pub fn spawn<F, C>(
+        console: SuperConsole,
+        frames_per_second: u32,
+        factory: F,
+    ) -> Result<Self, OutputError>
+    where
+        F: FnMut() -> C + Send + 'static,
+        C: Component<Error = E>,
+    {
+        let interval = Duration::from_secs(1) / frames_per_second.max(1);
+        let emitter = console.emitter();
+        let (control, requests) = unbounded();
+
+        let thread = std::thread::Builder::new()
+            .name("superconsole-render".to_owned())
+            .spawn(move || {
+                let mut console = console;
+                let mut factory = factory;
+                let mut next_frame = Instant::now();
+                let mut last_requested: Option<Instant> = None;
+                loop {
+                    match requests.recv_deadline(next_frame) {
+                        Ok(Control::Render) => {
+                            // Requests within an interval of the last requested render are left
+                            // to the next frame.
+                            if last_requested.is_none_or(|last| last.elapsed() >= interval) {
+                                last_requested = Some(Instant::now());
+                                console.render(&factory())?;
+                                next_frame = Instant::now() + interval;
+                            }
+                        }
+                        Ok(Control::Suspend(reply)) => {
+                            let _ignored = reply.send(console.suspend());
+                        }
+                        Ok(Control::Finish) | Err(RecvTimeoutError::Disconnected) => break,
+                        Err(RecvTimeoutError::Timeout) => {
+                            console.render(&factory())?;
+                            // Skip frames rather than catching up if rendering was slow.
+                            next_frame = cmp::max(next_frame + interval, Instant::now());
+                        }
+                    }
+                }
+                console.finalize(&factory())
+            })
+            .map_err(OutputError::SpawnThread)?;
+
+        Ok(Self {
+            emitter,
+            control: Some(control),
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns a handle that can be sent to other threads to emit lines.
+    pub fn emitter(&self) -> Emitter {
+        self.emitter.clone()
+    }
+
+    /// Queues the passed lines to be drawn on the next frame.
+    pub fn emit(&self, lines: Lines) -> Result<(), EmitError> {
+        self.emitter.emit(lines)
+    }
+
+    /// Queues the passed lines of auxillary output to be drawn on the next frame.
+    pub fn emit_aux(&self, lines: Lines) -> Result<(), EmitError> {
+        self.emitter.emit_aux(lines)
+    }
+
+    /// Asks for a render ahead of the next frame. Requests made within one frame interval of
+    /// the last requested render are merged into the next frame.
+    pub fn request_render(&self) {
+        if let Some(control) = &self.control {
+            let _ignored = control.send(Control::Render);
+        }
+    }
+
+    /// Suspends the console from the render thread, once it is done with the current frame.
+    /// See [`SuperConsole::suspend`]. The render loop keeps running, but draws nothing until the
+    /// guard is dropped.
+    pub fn suspend(&self) -> Result<SuspendGuard, OutputError> {
+        let (reply, replied) = bounded(1);
+        let control = self.control.as_ref().expect("only taken by finish or drop");
+        if control.send(Control::Suspend(reply)).is_ok()
+            && let Ok(res) = replied.recv()
+        {
+            return res;
+        }
+        Err(OutputError::Write(io::Error::other(
+            "the render loop has stopped",
+        )))
+    }
+
+    /// Stops the render loop after draining the lines emitted so far, performs the final render
+    /// with [`DrawMode::Final`](crate::DrawMode::Final) and waits for the console to be finalized.
+    ///
+    /// Returns the first error encountered by the render loop, if any.
+    pub fn finish(mut self) -> Result<(), Error<E>> {
+        drop(self.control.take());
+        let thread = self.thread.take().expect("thread is only taken by finish");
+        match thread.join() {
+            Ok(res) => res,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<E> Drop for RenderDriver<E> {
+    /// Ask the render loop to finalize the console, without waiting for it.
+    fn drop(&mut self) {
+        if let Some(control) = self.control.take() {
+            let _ignored = control.send(Control::Finish);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::Builder;
+    use crate::Dimensions;
+    use crate::DrawMode;
+    use crate::testing::SharedWriter;
+    use crate::testing::frame_contains;
+
+    struct Counter(usize);
+
+    impl Component for Counter {
+        type Error = Infallible;
+
+        fn draw_unchecked(
+            &self,
+            _dimensions: Dimensions,
+            mode: DrawMode,
+        ) -> Result<Lines, Infallible> {
+            let text = match mode {
+                DrawMode::Normal => format!("frame {}", self.0),
+                DrawMode::Final => "done".to_owned(),
+            };
+            Ok(Lines(vec![vec![text].try_into().unwrap()]))
+        }
+    }
+
+    fn console(writer: &SharedWriter) -> SuperConsole {
+        let mut builder = Builder::new();
+        builder.write_to(Box::new(writer.clone()));
+        builder.build_forced(Dimensions::new(80, 24)).unwrap()
+    }
+
+    #[test]
+    fn test_driver_renders_and_finalizes() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let frames = Arc::new(AtomicUsize::new(0));
+
+        let driver = RenderDriver::spawn(console(&writer), 100, {
+            let frames = frames.clone();
+            move || Counter(frames.fetch_add(1, Ordering::SeqCst))
+        })?;
+
+        let emitter = driver.emitter();
+        std::thread::spawn(move || {
+            for i in 0..10 {
+                emitter
+                    .emit(Lines(vec![vec![format!("line {i}")].try_into().unwrap()]))
+                    .unwrap();
+            }
+        })
+        .join()
+        .unwrap();
+
+        while frames.load(Ordering::SeqCst) < 3 {
+            std::thread::yield_now();
+        }
+        driver.finish()?;
+
+        let output = writer.0.lock().unwrap();
+        assert!(frame_contains(&output, "frame 0"));
+        assert!(frame_contains(&output, "done"));
+        for i in 0..10 {
+            assert!(frame_contains(&output, format!("line {i}")));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suspend() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let frames = Arc::new(AtomicUsize::new(0));
+
+        let driver = RenderDriver::spawn(console(&writer), 100, {
+            let frames = frames.clone();
+            move || Counter(frames.fetch_add(1, Ordering::SeqCst))
+        })?;
+
+        let guard = driver.suspend()?;
+        let written = writer.0.lock().unwrap().len();
+        let suspended_at = frames.load(Ordering::SeqCst);
+        // Frames are still produced, but nothing is drawn.
+        while frames.load(Ordering::SeqCst) < suspended_at + 3 {
+            std::thread::yield_now();
+        }
+        assert_eq!(writer.0.lock().unwrap().len(), written);
+
+        drop(guard);
+        driver.finish()?;
+        assert!(frame_contains(&writer.0.lock().unwrap()[written..], "done"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_emits_before_finish_are_drained() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+
+        // A very low frame rate, so that nothing but the initial frame is rendered before finish.
+        let driver = RenderDriver::spawn(console(&writer), 1, || Counter(0))?;
+        driver.emit(Lines(vec![vec!["line 1"].try_into()?]))?;
+        driver.emit(Lines(vec![vec!["line 2"].try_into()?]))?;
+        driver.finish()?;
+
+        let output = writer.0.lock().unwrap();
+        assert!(frame_contains(&output, "line 1"));
+        assert!(frame_contains(&output, "line 2"));
+        assert!(frame_contains(&output, "done"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_requests_are_merged() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let frames = Arc::new(AtomicUsize::new(0));
+
+        // One frame per second, so that only the initial frame is on the timer.
+        let driver = RenderDriver::spawn(console(&writer), 0, {
+            let frames = frames.clone();
+            move || Counter(frames.fetch_add(1, Ordering::SeqCst))
+        })?;
+        while frames.load(Ordering::SeqCst) < 1 {
+            std::thread::yield_now();
+        }
+
+        for _ in 0..5 {
+            driver.request_render();
+        }
+        // Requests are handled in order, so this waits for them.
+        drop(driver.suspend()?);
+        assert_eq!(frames.load(Ordering::SeqCst), 2);
+
+        driver.finish()?;
+        Ok(())
+    }
+}
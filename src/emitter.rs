@@ -0,0 +1,192 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! A handle to emit lines from any thread, without access to the [`SuperConsole`](crate::SuperConsole).
+
+use crossbeam_channel::SendError;
+use crossbeam_channel::Sender;
+use crossbeam_channel::TrySendError;
+
+use crate::Lines;
+use crate::error::EmitError;
+use crate::theme::Theme;
+
+/// Lines queued by an [`Emitter`], waiting to be drained by the console.
+pub(crate) enum Emitted {
+    Main(Lines),
+    Aux(Lines),
+}
+
+/// A cloneable handle that queues lines to be emitted by a [`SuperConsole`](crate::SuperConsole),
+/// obtained from [`SuperConsole::emitter`](crate::SuperConsole::emitter).
+///
+/// The queue is drained at the start of each render, after the lines passed to
+/// [`SuperConsole::emit`](crate::SuperConsole::emit) directly.
+/// If the queue is bounded (see [`Builder::emitter_capacity`](crate::Builder::emitter_capacity))
+/// and full, the lines are handed back with [`EmitError::Full`].
+#[derive(Clone)]
+pub struct Emitter {
+    pub(crate) sender: Sender<Emitted>,
+    pub(crate) theme: Theme,
+}
+
+impl Emitter {
+    /// The theme of the console, to style lines with outside of [`Component::draw`], where
+    /// [`Theme::current`] is plain.
+    ///
+    /// [`Component::draw`]: crate::Component::draw
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Queues the passed lines to be drawn on the next render.
+    pub fn emit(&self, lines: Lines) -> Result<(), EmitError> {
+        self.sender
+            .try_send(Emitted::Main(lines))
+            .map_err(|e| match e {
+                TrySendError::Full(Emitted::Main(lines)) => EmitError::Full(lines),
+                TrySendError::Disconnected(Emitted::Main(lines)) => EmitError::Disconnected(lines),
+                _ => unreachable!("sent main lines"),
+            })
+    }
+
+    /// Queues the passed lines of auxillary output to be drawn on the next render.
+    pub fn emit_aux(&self, lines: Lines) -> Result<(), EmitError> {
+        self.sender
+            .try_send(Emitted::Aux(lines))
+            .map_err(|e| match e {
+                TrySendError::Full(Emitted::Aux(lines)) => EmitError::Full(lines),
+                TrySendError::Disconnected(Emitted::Aux(lines)) => EmitError::Disconnected(lines),
+                _ => unreachable!("sent aux lines"),
+            })
+    }
+
+    /// Queues the passed lines to be drawn on the next render, waiting for room if the queue is
+    /// bounded and full.
+    pub fn emit_blocking(&self, lines: Lines) -> Result<(), EmitError> {
+        self.sender
+            .send(Emitted::Main(lines))
+            .map_err(|SendError(emitted)| match emitted {
+                Emitted::Main(lines) => EmitError::Disconnected(lines),
+                _ => unreachable!("sent main lines"),
+            })
+    }
+
+    /// Queues the passed lines of auxillary output to be drawn on the next render, waiting for
+    /// room if the queue is bounded and full.
+    pub fn emit_aux_blocking(&self, lines: Lines) -> Result<(), EmitError> {
+        self.sender
+            .send(Emitted::Aux(lines))
+            .map_err(|SendError(emitted)| match emitted {
+                Emitted::Aux(lines) => EmitError::Disconnected(lines),
+                _ => unreachable!("sent aux lines"),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SuperConsole;
+    use crate::components::echo::Echo;
+    use crate::superconsole::SuperConsoleConfig;
+    use crate::testing::SuperConsoleTestingExt;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+    use crate::testing::test_console_with_config;
+
+    fn line(text: &str) -> Lines {
+        Lines(vec![vec![text].try_into().unwrap()])
+    }
+
+    fn contents(console: &SuperConsole) -> Vec<u8> {
+        console.test_output().frames.concat()
+    }
+
+    #[test]
+    fn test_emit_from_threads() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let emitter = console.emitter();
+
+        std::thread::scope(|s| {
+            for i in 0..4 {
+                let emitter = emitter.clone();
+                s.spawn(move || emitter.emit(line(&format!("thread {i}"))).unwrap());
+            }
+        });
+        emitter.emit_aux(line("aux line"))?;
+        console.emit(line("direct"));
+        console.render(&Echo(line("state")))?;
+
+        let output = contents(&console);
+        for i in 0..4 {
+            assert!(frame_contains(&output, format!("thread {i}")));
+        }
+        assert!(frame_contains(&output, "aux line"));
+        assert!(frame_contains(&output, "state"));
+
+        // Lines emitted directly come first.
+        let text = String::from_utf8_lossy(&output);
+        assert!(text.find("direct").unwrap() < text.find("thread").unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounded_emitter() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            emitter_capacity: Some(2),
+            ..Default::default()
+        });
+        let emitter = console.emitter();
+
+        emitter.emit(line("line 1"))?;
+        emitter.emit(line("line 2"))?;
+        match emitter.emit(line("line 3")) {
+            Err(EmitError::Full(lines)) => assert_eq!(lines, line("line 3")),
+            res => panic!("expected the queue to be full, got {res:?}"),
+        }
+
+        // Rendering drains the queue.
+        console.render(&Echo(line("state")))?;
+        emitter.emit(line("line 3"))?;
+
+        drop(console);
+        assert!(matches!(
+            emitter.emit(line("line 4")),
+            Err(EmitError::Disconnected(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_emit_blocking() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            emitter_capacity: Some(1),
+            ..Default::default()
+        });
+        let emitter = console.emitter();
+        emitter.emit(line("line 1"))?;
+
+        let blocked = std::thread::spawn(move || emitter.emit_blocking(line("line 2")));
+        while !blocked.is_finished() {
+            console.render(&Echo(line("state")))?;
+        }
+        blocked.join().unwrap()?;
+        console.render(&Echo(line("state")))?;
+
+        let output = contents(&console);
+        assert!(frame_contains(&output, "line 1"));
+        assert!(frame_contains(&output, "line 2"));
+
+        Ok(())
+    }
+}
@@ -8,6 +8,8 @@
  * above-listed licenses.
  */
 
+use crate::Lines;
+
 /// An error writing to a stdout or stderr stream
 #[derive(thiserror::Error, Debug)]
 pub enum OutputError {
@@ -17,6 +19,20 @@ pub enum OutputError {
     SpawnThread(std::io::Error),
     #[error("Error interacting with terminal: {0}")]
     Terminal(std::io::Error),
+    #[error("Error capturing stdout or stderr: {0}")]
+    Capture(std::io::Error),
+    #[error("Error installing signal handler: {0}")]
+    Signals(std::io::Error),
+}
+
+/// An error queueing lines through an [`Emitter`](crate::emitter::Emitter).
+/// The lines that could not be queued are handed back.
+#[derive(thiserror::Error, Debug)]
+pub enum EmitError {
+    #[error("The emit queue is full")]
+    Full(Lines),
+    #[error("The console was dropped")]
+    Disconnected(Lines),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -37,18 +37,33 @@ pub use error::Error;
 
 pub use crate::builder::Builder;
 pub use crate::superconsole::SuperConsole;
+pub use crate::superconsole::SuspendGuard;
 
 #[allow(type_alias_bounds)]
 pub type RenderResult<T, C: Component> = Result<T, Error<<C as Component>::Error>>;
 
 pub(crate) mod ansi_support;
+pub mod asciicast;
+#[cfg(feature = "tokio")]
+pub mod async_driver;
 pub mod builder;
+#[cfg(unix)]
+mod capture;
 pub mod components;
 pub mod content;
 mod dimensions;
+pub mod driver;
+pub mod emitter;
 pub mod error;
+#[cfg(feature = "log")]
+pub mod logger;
 pub mod output;
+mod restore;
+pub mod stats;
 pub mod style;
 mod superconsole;
 pub mod testing;
+pub mod theme;
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
 pub(crate) mod vec_as_fmt_write;
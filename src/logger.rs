@@ -0,0 +1,225 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! A [`log::Log`] implementation that emits records above the canvas.
+
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use log::LevelFilter;
+use log::Log;
+use log::Metadata;
+use log::Record;
+use log::SetLoggerError;
+
+use crate::Line;
+use crate::Lines;
+use crate::Span;
+use crate::emitter::Emitter;
+use crate::style::StyledContent;
+
+/// Formats each record as `[timestamp] LEVEL target: message`, styled with the console's
+/// [`Theme`](crate::theme::Theme), and queues it through an [`Emitter`] to be drawn on the next
+/// render. This works regardless of the output the console uses, as the logging thread never
+/// writes to it.
+///
+/// Records are dropped if the emitter is bounded and full, or once the console is gone.
+pub struct SuperConsoleLogger {
+    emitter: Emitter,
+    level: LevelFilter,
+    with_target: bool,
+    with_timestamp: bool,
+}
+
+impl SuperConsoleLogger {
+    pub fn new(emitter: Emitter) -> Self {
+        Self {
+            emitter,
+            level: LevelFilter::Info,
+            with_target: true,
+            with_timestamp: false,
+        }
+    }
+
+    /// The most verbose level to emit. `Info` by default.
+    pub fn level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Whether to include the target of each record. Enabled by default.
+    pub fn with_target(mut self, with_target: bool) -> Self {
+        self.with_target = with_target;
+        self
+    }
+
+    /// Whether to prefix each record with the UTC time of day it was logged at. Disabled by default.
+    pub fn with_timestamp(mut self, with_timestamp: bool) -> Self {
+        self.with_timestamp = with_timestamp;
+        self
+    }
+
+    /// Installs this as the global logger, and sets the maximum log level to match.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+
+    fn format(&self, record: &Record<'_>) -> Lines {
+        let level = record.level();
+        let theme = self.emitter.theme();
+
+        let mut first = Line::default();
+        if self.with_timestamp {
+            first.push(Span::new_styled_lossy(StyledContent::new(
+                theme.log_context(),
+                time_of_day(SystemTime::now()),
+            )));
+            first.push(Span::padding(1));
+        }
+        first.push(Span::new_styled_lossy(StyledContent::new(
+            theme.level(level.into()),
+            format!("{level:>5}"),
+        )));
+        first.push(Span::padding(1));
+        if self.with_target {
+            first.push(Span::new_styled_lossy(StyledContent::new(
+                theme.log_context(),
+                format!("{}:", record.target()),
+            )));
+            first.push(Span::padding(1));
+        }
+
+        let message = record.args().to_string();
+        let mut texts = message.lines();
+        first.push(Span::new_unstyled_lossy(texts.next().unwrap_or_default()));
+
+        let mut lines = Lines(vec![first]);
+        lines.extend(texts.map(Line::sanitized));
+        lines
+    }
+}
+
+/// Formats the UTC time of day as `HH:MM:SS.mmm`.
+fn time_of_day(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() % (24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+impl Log for SuperConsoleLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            let _ignored = self.emitter.emit(self.format(record));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use log::Level;
+
+    use super::*;
+    use crate::Builder;
+    use crate::Dimensions;
+    use crate::components::echo::Echo;
+    use crate::style::Stylize;
+    use crate::testing::SharedWriter;
+    use crate::testing::SuperConsoleTestingExt;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+
+    fn log(logger: &SuperConsoleLogger, level: Level, message: &str) {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target("my_crate")
+                .args(format_args!("{message}"))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn test_levels_are_filtered() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let logger = SuperConsoleLogger::new(console.emitter()).level(LevelFilter::Warn);
+
+        log(&logger, Level::Error, "an error");
+        log(&logger, Level::Warn, "a warning");
+        log(&logger, Level::Info, "some info");
+        console.render(&Echo(Lines::new()))?;
+
+        let frame = console.test_output().frames.concat();
+        let text = String::from_utf8_lossy(&frame);
+        assert!(text.find("an error").unwrap() < text.find("a warning").unwrap());
+        assert!(text.contains("my_crate:"));
+        assert!(!text.contains("some info"));
+
+        let mut level = Vec::new();
+        Line::from_iter([Span::new_styled_lossy(" WARN".to_owned().yellow().bold())])
+            .render_spans(&mut level);
+        assert!(frame_contains(&frame, level));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(
+            time_of_day(UNIX_EPOCH + Duration::from_millis(((13 * 60 + 5) * 60 + 9) * 1000 + 42)),
+            "13:05:09.042"
+        );
+
+        let logger = SuperConsoleLogger::new(test_console().emitter())
+            .with_timestamp(true)
+            .with_target(false);
+        let record = Record::builder()
+            .level(Level::Info)
+            .args(format_args!("message"))
+            .build();
+        let line = logger.format(&record).0[0].to_unstyled();
+        assert_eq!(line.len(), "00:00:00.000  INFO message".len());
+        assert!(line.ends_with("  INFO message"));
+    }
+
+    #[test]
+    fn test_non_blocking_console() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut builder = Builder::new();
+        builder.non_blocking().write_to(Box::new(writer.clone()));
+        let console = builder.build_forced(Dimensions::new(80, 24))?;
+
+        let logger = SuperConsoleLogger::new(console.emitter());
+        std::thread::spawn(move || log(&logger, Level::Info, "from a thread"))
+            .join()
+            .unwrap();
+        console.finalize(&Echo(Lines::new()))?;
+
+        assert!(frame_contains(&writer.0.lock().unwrap(), "from a thread"));
+
+        Ok(())
+    }
+}
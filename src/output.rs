@@ -9,7 +9,9 @@
  */
 
 use std::any::Any;
+use std::io;
 use std::io::Write;
+use std::iter;
 use std::thread::JoinHandle;
 
 use crossbeam_channel::Receiver;
@@ -51,6 +53,12 @@ pub trait SuperConsoleOutput: Send + Sync + 'static {
         self.output(buffer)
     }
 
+    /// Called to wait until everything passed to `output` and `output_to` so far was written out.
+    /// Outputs that write synchronously don't need to do anything.
+    fn flush(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
     /// Check if auxillary stream is tty
     fn aux_stream_is_tty(&self) -> bool {
         true
@@ -74,11 +82,180 @@ pub trait SuperConsoleOutput: Send + Sync + 'static {
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
+/// Reports the size of the terminal the console is drawn on, in place of the output. This lets
+/// hosts that write to a remote terminal, such as a pty server, report the size of that terminal.
+/// Set with [`Builder::size_provider`](crate::Builder::size_provider).
+pub trait SizeProvider: Send + Sync + 'static {
+    fn size(&self) -> Result<Dimensions, OutputError>;
+}
+
+impl<F> SizeProvider for F
+where
+    F: Fn() -> Result<Dimensions, OutputError> + Send + Sync + 'static,
+{
+    fn size(&self) -> Result<Dimensions, OutputError> {
+        self()
+    }
+}
+
+/// Forwards to an inner output, but takes the terminal size from a [`SizeProvider`].
+pub(crate) struct SizedOutput {
+    pub(crate) inner: Box<dyn SuperConsoleOutput>,
+    pub(crate) size: Box<dyn SizeProvider>,
+}
+
+impl SuperConsoleOutput for SizedOutput {
+    fn should_render(&mut self) -> bool {
+        self.inner.should_render()
+    }
+
+    fn output(&mut self, buffer: Vec<u8>) -> Result<(), OutputError> {
+        self.inner.output(buffer)
+    }
+
+    fn output_to(&mut self, buffer: Vec<u8>, target: OutputTarget) -> Result<(), OutputError> {
+        self.inner.output_to(buffer, target)
+    }
+
+    fn flush(&mut self) -> Result<(), OutputError> {
+        self.inner.flush()
+    }
+
+    fn aux_stream_is_tty(&self) -> bool {
+        self.inner.aux_stream_is_tty()
+    }
+
+    fn terminal_size(&self) -> Result<Dimensions, OutputError> {
+        self.size.size()
+    }
+
+    fn finalize(self: Box<Self>) -> Result<(), OutputError> {
+        self.inner.finalize()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The state of a scan through a frame, used to find where it can be split.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    /// Outside of any escape sequence.
+    Ground,
+    /// After an `ESC`, possibly followed by intermediate bytes.
+    Escape,
+    /// Inside a control sequence (`ESC [`), up to its final byte.
+    Csi,
+    /// Inside a string (`ESC ]`, `ESC P`, ...), up to `BEL` or `ESC \`.
+    String,
+    /// After an `ESC` inside a string.
+    StringEscape,
+}
+
+impl EscapeState {
+    fn advance(self, byte: u8) -> Self {
+        const ESC: u8 = 0x1b;
+        const BEL: u8 = 0x07;
+        match (self, byte) {
+            (Self::Ground, ESC) => Self::Escape,
+            (Self::Ground, _) => Self::Ground,
+            (Self::Escape, b'[') => Self::Csi,
+            (Self::Escape, b']' | b'P' | b'X' | b'^' | b'_') => Self::String,
+            (Self::Escape, 0x20..=0x2f) => Self::Escape,
+            (Self::Escape, _) => Self::Ground,
+            (Self::Csi, 0x40..=0x7e) => Self::Ground,
+            (Self::Csi, _) => Self::Csi,
+            (Self::String, BEL) => Self::Ground,
+            (Self::String, ESC) => Self::StringEscape,
+            (Self::String, _) => Self::String,
+            (Self::StringEscape, b'\\') => Self::Ground,
+            (Self::StringEscape, _) => Self::String,
+        }
+    }
+}
+
+/// The length of the first chunk of `frame`, at most `max_chunk_size` bytes unless a single escape
+/// sequence is longer than that. Never cuts through an escape sequence or a UTF-8 character, and
+/// prefers to cut after a newline if that doesn't make the chunk much smaller.
+fn chunk_len(frame: &[u8], max_chunk_size: usize) -> usize {
+    if frame.len() <= max_chunk_size {
+        return frame.len();
+    }
+
+    let mut state = EscapeState::Ground;
+    let mut last_boundary = None;
+    let mut last_line = None;
+    let mut next_boundary = None;
+    for (i, &byte) in frame.iter().enumerate() {
+        // UTF-8 continuation bytes are 0b10xxxxxx.
+        if i > 0 && state == EscapeState::Ground && byte & 0xc0 != 0x80 {
+            if i > max_chunk_size {
+                next_boundary = Some(i);
+                break;
+            }
+            last_boundary = Some(i);
+            if frame[i - 1] == b'\n' {
+                last_line = Some(i);
+            }
+        }
+        state = state.advance(byte);
+    }
+
+    last_line
+        .filter(|&line| line > max_chunk_size / 2)
+        .or(last_boundary)
+        // Nothing fits, so take everything up to the first boundary.
+        .or(next_boundary)
+        .unwrap_or(frame.len())
+}
+
+/// Splits a frame into chunks that can each be written in a single write, so that a slow reader
+/// never sees a frame cut in the middle of an escape sequence. See [`chunk_len`].
+pub(crate) fn frame_chunks(frame: &[u8], max_chunk_size: usize) -> impl Iterator<Item = &[u8]> {
+    let mut rest = frame;
+    iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let (chunk, tail) = rest.split_at(chunk_len(rest, max_chunk_size));
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// Writes and flushes a frame, in chunks of at most `max_chunk_size` bytes if set.
+fn write_frame(
+    stream: &mut dyn Write,
+    frame: &[u8],
+    max_chunk_size: Option<usize>,
+) -> io::Result<()> {
+    match max_chunk_size {
+        Some(max_chunk_size) => {
+            for chunk in frame_chunks(frame, max_chunk_size) {
+                stream.write_all(chunk)?;
+                stream.flush()?;
+            }
+            Ok(())
+        }
+        None => {
+            stream.write_all(frame)?;
+            stream.flush()
+        }
+    }
+}
+
 pub struct BlockingSuperConsoleOutput {
     /// Stream to write to.
     stream: Box<dyn Write + Send + 'static + Sync>,
     /// Auxiliary stream to write to.
     aux_stream: Box<dyn IsTtyWrite + Send + 'static + Sync>,
+    /// Maximum number of bytes to write at once, if any.
+    max_chunk_size: Option<usize>,
 }
 
 impl BlockingSuperConsoleOutput {
@@ -86,7 +263,18 @@ impl BlockingSuperConsoleOutput {
         stream: Box<dyn Write + Send + 'static + Sync>,
         aux_stream: Box<dyn IsTtyWrite + Send + 'static + Sync>,
     ) -> Self {
-        Self { stream, aux_stream }
+        Self {
+            stream,
+            aux_stream,
+            max_chunk_size: None,
+        }
+    }
+
+    /// Split each frame into writes of at most `max_chunk_size` bytes, cutting only between escape
+    /// sequences. Writes of up to `PIPE_BUF` bytes (at least 512) to a pipe are never interleaved.
+    pub fn with_max_chunk_size(mut self, max_chunk_size: usize) -> Self {
+        self.max_chunk_size = Some(max_chunk_size);
+        self
     }
 }
 
@@ -104,19 +292,11 @@ impl SuperConsoleOutput for BlockingSuperConsoleOutput {
     }
 
     fn output_to(&mut self, buffer: Vec<u8>, target: OutputTarget) -> Result<(), OutputError> {
-        match target {
-            OutputTarget::Main => {
-                self.stream.write_all(&buffer).map_err(OutputError::Write)?;
-                self.stream.flush().map_err(OutputError::Write)?;
-            }
-            OutputTarget::Aux => {
-                self.aux_stream
-                    .write_all(&buffer)
-                    .map_err(OutputError::Write)?;
-                self.aux_stream.flush().map_err(OutputError::Write)?;
-            }
-        }
-        Ok(())
+        let stream = match target {
+            OutputTarget::Main => &mut self.stream as &mut dyn Write,
+            OutputTarget::Aux => &mut self.aux_stream,
+        };
+        write_frame(stream, &buffer, self.max_chunk_size).map_err(OutputError::Write)
     }
 
     fn finalize(self: Box<Self>) -> Result<(), OutputError> {
@@ -132,6 +312,13 @@ impl SuperConsoleOutput for BlockingSuperConsoleOutput {
     }
 }
 
+/// A message to the thread doing the writing for [`NonBlockingSuperConsoleOutput`].
+enum WriterMessage {
+    Write(Vec<u8>, OutputTarget),
+    /// Acknowledged once everything sent before was written.
+    Flush(Sender<()>),
+}
+
 /// A non-blocking output for the SuperConsole. This makes a few guarantees:
 ///
 /// - Calls to output() after should_render() returned true will not block.
@@ -139,7 +326,7 @@ impl SuperConsoleOutput for BlockingSuperConsoleOutput {
 /// - When an error occurs, the next fallible call will return it.
 pub(crate) struct NonBlockingSuperConsoleOutput {
     /// A channel to send frames for writing.
-    sender: Sender<(Vec<u8>, OutputTarget)>,
+    sender: Sender<WriterMessage>,
     /// A channel back for errors encountered by the thread doing the writing.
     errors: Receiver<OutputError>,
     /// The thread doing the writing. It owns the other end of the aforementioned channels and will
@@ -153,27 +340,36 @@ impl NonBlockingSuperConsoleOutput {
     pub(crate) fn new(
         stream: Box<dyn Write + Send + 'static + Sync>,
         aux_stream: Box<dyn IsTtyWrite + Send + 'static + Sync>,
+        max_chunk_size: Option<usize>,
     ) -> Result<Self, OutputError> {
-        Self::new_for_writer(stream, aux_stream)
+        Self::new_for_writer(stream, aux_stream, max_chunk_size)
     }
 
     fn new_for_writer(
         mut stream: Box<dyn Write + Send + 'static + Sync>,
         mut aux_stream: Box<dyn IsTtyWrite + Send + 'static + Sync>,
+        max_chunk_size: Option<usize>,
     ) -> Result<Self, OutputError> {
-        let (sender, receiver) = bounded::<(Vec<u8>, OutputTarget)>(1);
+        let (sender, receiver) = bounded::<WriterMessage>(1);
         let (error_sender, errors) = unbounded::<OutputError>();
         let aux_compatible = aux_stream.is_tty();
 
         let handle = std::thread::Builder::new()
             .name("superconsole-io".to_owned())
             .spawn(move || {
-                for (data, output_target) in receiver.into_iter() {
+                for message in receiver.into_iter() {
+                    let (data, output_target) = match message {
+                        WriterMessage::Write(data, output_target) => (data, output_target),
+                        WriterMessage::Flush(ack) => {
+                            let _ignored = ack.send(());
+                            continue;
+                        }
+                    };
                     let out_stream = match output_target {
                         OutputTarget::Main => &mut stream,
                         OutputTarget::Aux => &mut aux_stream as &mut dyn Write,
                     };
-                    match out_stream.write_all(&data).and_then(|()| stream.flush()) {
+                    match write_frame(out_stream, &data, max_chunk_size) {
                         Ok(()) => {}
                         Err(e) => {
                             // This can only fail if the sender disconnected, in which case they'll
@@ -214,12 +410,26 @@ impl SuperConsoleOutput for NonBlockingSuperConsoleOutput {
         }
 
         self.sender
-            .send((buffer, target))
+            .send(WriterMessage::Write(buffer, target))
             .expect("Superconsole I/O thread crashed");
 
         Ok(())
     }
 
+    /// Wait for our writer thread to have written out everything sent so far.
+    fn flush(&mut self) -> Result<(), OutputError> {
+        let (ack, acked) = bounded(1);
+        self.sender
+            .send(WriterMessage::Flush(ack))
+            .expect("Superconsole I/O thread crashed");
+        let _ignored = acked.recv();
+
+        match self.errors.try_recv() {
+            Ok(err) => Err(err),
+            Err(_) => Ok(()),
+        }
+    }
+
     fn aux_stream_is_tty(&self) -> bool {
         self.aux_compatible
     }
@@ -313,6 +523,7 @@ mod tests {
             let mut output = NonBlockingSuperConsoleOutput::new_for_writer(
                 Box::new(writer),
                 Box::new(aux_writer),
+                None,
             )?;
 
             // Send a first message, this will go into write()
@@ -345,4 +556,110 @@ mod tests {
             }
         }
     }
+
+    fn chunks(frame: &[u8], max_chunk_size: usize) -> Vec<String> {
+        let chunks: Vec<_> = frame_chunks(frame, max_chunk_size).collect();
+        assert_eq!(chunks.concat(), frame);
+        chunks
+            .into_iter()
+            .map(|chunk| String::from_utf8(chunk.to_vec()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_frame_chunks_keep_escape_sequences() {
+        let frame = b"ab\x1b[31mcd\x1b]8;;http://example.com\x1b\\ef";
+        assert_eq!(
+            chunks(frame, 4),
+            [
+                "ab",
+                "\x1b[31m",
+                "cd",
+                "\x1b]8;;http://example.com\x1b\\",
+                "ef"
+            ]
+        );
+        assert_eq!(chunks(frame, 100), [std::str::from_utf8(frame).unwrap()]);
+    }
+
+    #[test]
+    fn test_frame_chunks_keep_characters() {
+        assert_eq!(chunks("a\u{1f9b6}b".as_bytes(), 3), ["a", "\u{1f9b6}", "b"]);
+    }
+
+    #[test]
+    fn test_frame_chunks_prefer_line_ends() {
+        assert_eq!(
+            chunks(b"line 1\nline 2\nline 3\n", 10),
+            ["line 1\n", "line 2\n", "line 3\n"]
+        );
+        // Unless that would make the chunk much smaller than needed.
+        assert_eq!(chunks(b"a\nlong line\n", 10), ["a\nlong lin", "e\n"]);
+    }
+
+    /// A writer recording each write separately.
+    #[derive(Clone, Default)]
+    struct RecordingWriter(std::sync::Arc<std::sync::Mutex<Vec<Vec<u8>>>>);
+
+    impl IsTty for RecordingWriter {
+        fn is_tty(&self) -> bool {
+            true
+        }
+    }
+
+    impl Write for RecordingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_outputs_write_chunks() -> anyhow::Result<()> {
+        let frame = b"line 1\nline 2\n".to_vec();
+        let expected = [b"line 1\n".to_vec(), b"line 2\n".to_vec()];
+
+        let writer = RecordingWriter::default();
+        let mut output =
+            BlockingSuperConsoleOutput::new(Box::new(writer.clone()), Box::new(writer.clone()))
+                .with_max_chunk_size(8);
+        output.output(frame.clone())?;
+        assert_eq!(*writer.0.lock().unwrap(), expected);
+
+        let writer = RecordingWriter::default();
+        let mut output = NonBlockingSuperConsoleOutput::new(
+            Box::new(writer.clone()),
+            Box::new(writer.clone()),
+            Some(8),
+        )?;
+        output.output_to(frame, OutputTarget::Aux)?;
+        Box::new(output).finalize()?;
+        assert_eq!(*writer.0.lock().unwrap(), expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_blocking_flush() -> anyhow::Result<()> {
+        let writer = RecordingWriter::default();
+        let mut output = NonBlockingSuperConsoleOutput::new(
+            Box::new(writer.clone()),
+            Box::new(writer.clone()),
+            None,
+        )?;
+
+        output.output(b"frame 1".to_vec())?;
+        output.output(b"frame 2".to_vec())?;
+        output.flush()?;
+        assert_eq!(
+            *writer.0.lock().unwrap(),
+            [b"frame 1".to_vec(), b"frame 2".to_vec()]
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,517 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Restores the terminal when the process panics or is interrupted while a canvas is displayed,
+//! so that the panic message or whatever comes next doesn't land in the middle of it.
+
+use std::any::Any;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::Once;
+use std::sync::TryLockError;
+use std::sync::Weak;
+use std::thread::ThreadId;
+use std::time::Duration;
+use std::time::Instant;
+
+use crossterm::QueueableCommand;
+use crossterm::cursor::Show;
+use crossterm::terminal::LeaveAlternateScreen;
+
+use crate::Dimensions;
+#[cfg(unix)]
+use crate::capture::PausedCapture;
+#[cfg(unix)]
+use crate::capture::SavedStdio;
+use crate::error::OutputError;
+use crate::output::OutputTarget;
+use crate::output::SuperConsoleOutput;
+use crate::superconsole::SuperConsole;
+
+/// How long to wait for a write in progress on another thread before restoring the terminal.
+/// The lock may be held by the panicking thread itself, so we can't wait forever.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long to wait for the output to write out what restores the terminal. It may be stuck
+/// writing to a terminal nobody reads from, so we can't wait forever.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Consoles to restore when the process panics.
+static ON_PANIC: Mutex<Vec<Weak<Restore>>> = Mutex::new(Vec::new());
+
+/// Consoles to restore when the process receives SIGINT or SIGTERM.
+#[cfg(all(unix, feature = "signals"))]
+static ON_SIGNAL: Mutex<Vec<Weak<Restore>>> = Mutex::new(Vec::new());
+
+/// The output of a console, along with what is on screen, shared with the panic hook and the
+/// signal handler so that they can restore the terminal while the console is owned elsewhere.
+pub(crate) struct Restore {
+    state: Mutex<RestoreState>,
+}
+
+struct RestoreState {
+    /// Taken by the console's finalize, or lost if it didn't flush in time when restoring.
+    output: Option<Box<dyn SuperConsoleOutput>>,
+    /// The number of rows above the cursor taken by the canvas.
+    canvas_rows: usize,
+    in_alternate_screen: bool,
+    cursor_hidden: bool,
+    /// The thread that drew the console last.
+    owner: Option<ThreadId>,
+    /// Set when the terminal was restored, until the console notices.
+    restored: bool,
+    /// The original stdout and stderr, if the console captured them.
+    #[cfg(unix)]
+    stdio: Option<SavedStdio>,
+}
+
+impl Restore {
+    pub(crate) fn new(output: Box<dyn SuperConsoleOutput>) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(RestoreState {
+                output: Some(output),
+                canvas_rows: 0,
+                in_alternate_screen: false,
+                cursor_hidden: false,
+                owner: None,
+                restored: false,
+                #[cfg(unix)]
+                stdio: None,
+            }),
+        })
+    }
+
+    /// Restores `this` when the thread that drew it last panics, before the panic message is
+    /// printed. Panics on other threads leave it alone.
+    pub(crate) fn on_panic(this: &Arc<Self>) {
+        static HOOK: Once = Once::new();
+        register(&ON_PANIC, this);
+        HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                let restored = restore_all(&ON_PANIC, Some(std::thread::current().id()));
+                previous(info);
+                // In case the panic is caught, and the console keeps going.
+                for restored in restored {
+                    restored.resume();
+                }
+            }));
+        });
+    }
+
+    /// Restores `this` when the process receives SIGINT or SIGTERM, before terminating it as the
+    /// signal would have by default.
+    #[cfg(all(unix, feature = "signals"))]
+    pub(crate) fn on_signals(this: &Arc<Self>) -> Result<(), OutputError> {
+        use signal_hook::consts::SIGINT;
+        use signal_hook::consts::SIGTERM;
+        use signal_hook::iterator::Signals;
+
+        static HANDLER: Mutex<bool> = Mutex::new(false);
+        let mut installed = lock(&HANDLER);
+        if !*installed {
+            let mut signals = Signals::new([SIGINT, SIGTERM]).map_err(OutputError::Signals)?;
+            std::thread::Builder::new()
+                .name("superconsole-signals".to_owned())
+                .spawn(move || {
+                    for signal in signals.forever() {
+                        restore_all(&ON_SIGNAL, None);
+                        let _ignored = signal_hook::low_level::emulate_default_handler(signal);
+                    }
+                })
+                .map_err(OutputError::SpawnThread)?;
+            *installed = true;
+        }
+        register(&ON_SIGNAL, this);
+        Ok(())
+    }
+
+    /// Points stdout and stderr back at `stdio` when restoring, so that the panic message isn't
+    /// captured.
+    #[cfg(unix)]
+    pub(crate) fn set_stdio(&self, stdio: SavedStdio) {
+        lock(&self.state).stdio = Some(stdio);
+    }
+
+    /// Records what the console last drew, and from which thread.
+    pub(crate) fn set_screen(
+        &self,
+        canvas_rows: usize,
+        in_alternate_screen: bool,
+        cursor_hidden: bool,
+    ) {
+        let mut state = lock(&self.state);
+        state.canvas_rows = canvas_rows;
+        state.in_alternate_screen = in_alternate_screen;
+        state.cursor_hidden = cursor_hidden;
+        state.owner = Some(std::thread::current().id());
+    }
+
+    /// Whether the terminal was restored since the last call. The console then starts over
+    /// below whatever was printed since.
+    pub(crate) fn take_restored(&self) -> bool {
+        std::mem::take(&mut lock(&self.state).restored)
+    }
+
+    /// Clears the canvas, or leaves the alternate screen, shows the cursor, points stdout and
+    /// stderr back at the original files, and waits a little for everything written so far to be
+    /// flushed. The console keeps drawing afterwards, unless the output didn't flush in time.
+    ///
+    /// Only does so if the console was last drawn by `owner`, or regardless if `None`.
+    pub(crate) fn restore(&self, owner: Option<ThreadId>) -> Restored {
+        let mut restored = Restored::default();
+        let Some(mut state) = lock_with_timeout(&self.state) else {
+            return restored;
+        };
+        if owner.is_some() && state.owner != owner {
+            return restored;
+        }
+        let Some(output) = state.output.take() else {
+            return restored;
+        };
+        #[cfg(unix)]
+        if let Some(stdio) = &state.stdio {
+            restored.paused = stdio.pause().ok();
+            if restored.paused.is_none() {
+                stdio.restore();
+            }
+        }
+
+        let mut buffer = Vec::new();
+        if state.in_alternate_screen {
+            let _ignored = buffer.queue(LeaveAlternateScreen);
+        } else if state.canvas_rows > 0 {
+            let _ignored = SuperConsole::clear_canvas_pre(&mut buffer, state.canvas_rows);
+            let _ignored = SuperConsole::clear_canvas_post(&mut buffer);
+        }
+        if state.cursor_hidden {
+            let _ignored = buffer.queue(Show);
+        }
+        state.canvas_rows = 0;
+        state.in_alternate_screen = false;
+        state.cursor_hidden = false;
+        state.restored = true;
+        state.output = flush_with_timeout(output, buffer);
+        restored
+    }
+}
+
+/// What to undo once the panic message is printed.
+#[derive(Default)]
+pub(crate) struct Restored {
+    #[cfg(unix)]
+    paused: Option<PausedCapture>,
+}
+
+impl Restored {
+    /// Captures stdout and stderr again if they were.
+    fn resume(self) {
+        #[cfg(unix)]
+        if let Some(paused) = self.paused {
+            paused.resume();
+        }
+    }
+}
+
+/// Writes `buffer` from another thread, and waits for it to be flushed for at most
+/// [`FLUSH_TIMEOUT`]. Returns the output, unless it took too long.
+fn flush_with_timeout(
+    mut output: Box<dyn SuperConsoleOutput>,
+    buffer: Vec<u8>,
+) -> Option<Box<dyn SuperConsoleOutput>> {
+    let (done, flushed) = crossbeam_channel::bounded(1);
+    std::thread::Builder::new()
+        .name("superconsole-restore".to_owned())
+        .spawn(move || {
+            if !buffer.is_empty() {
+                let _ignored = output.output(buffer);
+            }
+            let _ignored = output.flush();
+            let _ignored = done.send(output);
+        })
+        .ok()?;
+    flushed.recv_timeout(FLUSH_TIMEOUT).ok()
+}
+
+fn register(consoles: &Mutex<Vec<Weak<Restore>>>, restore: &Arc<Restore>) {
+    let mut consoles = lock(consoles);
+    consoles.retain(|console| console.strong_count() > 0);
+    consoles.push(Arc::downgrade(restore));
+}
+
+/// Restores the consoles last drawn by `owner`, or all of them.
+fn restore_all(consoles: &Mutex<Vec<Weak<Restore>>>, owner: Option<ThreadId>) -> Vec<Restored> {
+    let Some(consoles) = lock_with_timeout(consoles) else {
+        return Vec::new();
+    };
+    consoles
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|restore| restore.restore(owner))
+        .collect()
+}
+
+/// Locks `mutex`, ignoring poisoning: restoring the terminal is all the more useful after a panic.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn lock_with_timeout<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    let deadline = Instant::now() + LOCK_TIMEOUT;
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Some(guard),
+            Err(TryLockError::Poisoned(poisoned)) => return Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Err(TryLockError::WouldBlock) => return None,
+        }
+    }
+}
+
+/// The output of a console that can be restored, forwarding to the output in [`Restore`] until
+/// it is taken.
+pub(crate) struct RestorableOutput(pub(crate) Arc<Restore>);
+
+impl RestorableOutput {
+    fn with_output<R>(&self, restored: R, f: impl FnOnce(&mut dyn SuperConsoleOutput) -> R) -> R {
+        match lock(&self.0.state).output.as_deref_mut() {
+            Some(output) => f(output),
+            None => restored,
+        }
+    }
+}
+
+impl SuperConsoleOutput for RestorableOutput {
+    fn should_render(&mut self) -> bool {
+        self.with_output(false, |output| output.should_render())
+    }
+
+    fn output(&mut self, buffer: Vec<u8>) -> Result<(), OutputError> {
+        self.with_output(Ok(()), |output| output.output(buffer))
+    }
+
+    fn output_to(&mut self, buffer: Vec<u8>, target: OutputTarget) -> Result<(), OutputError> {
+        self.with_output(Ok(()), |output| output.output_to(buffer, target))
+    }
+
+    fn flush(&mut self) -> Result<(), OutputError> {
+        self.with_output(Ok(()), |output| output.flush())
+    }
+
+    fn aux_stream_is_tty(&self) -> bool {
+        self.with_output(true, |output| output.aux_stream_is_tty())
+    }
+
+    fn terminal_size(&self) -> Result<Dimensions, OutputError> {
+        // Nothing is drawn anymore once the output is gone.
+        self.with_output(Ok(Dimensions::default()), |output| output.terminal_size())
+    }
+
+    fn finalize(self: Box<Self>) -> Result<(), OutputError> {
+        match lock(&self.0.state).output.take() {
+            Some(output) => output.finalize(),
+            None => Ok(()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use crossterm::cursor::MoveToColumn;
+    use crossterm::cursor::MoveUp;
+    use crossterm::terminal::Clear;
+    use crossterm::terminal::ClearType;
+
+    use super::*;
+    use crate::Builder;
+    use crate::DrawMode;
+    use crate::Lines;
+    use crate::components::Component;
+    use crate::components::echo::Echo;
+    use crate::testing::SharedWriter;
+    use crate::testing::frame_contains;
+
+    fn console(writer: &SharedWriter, configure: impl FnOnce(&mut Builder)) -> SuperConsole {
+        let mut builder = Builder::new();
+        builder.write_to(Box::new(writer.clone()));
+        configure(&mut builder);
+        builder.build_forced(Dimensions::new(80, 24)).unwrap()
+    }
+
+    fn canvas() -> anyhow::Result<Echo> {
+        Ok(Echo(Lines(vec![
+            vec!["line 1"].try_into()?,
+            vec!["line 2"].try_into()?,
+        ])))
+    }
+
+    fn cleared(rows: u16) -> anyhow::Result<Vec<u8>> {
+        let mut expected = Vec::new();
+        expected.queue(MoveUp(rows))?;
+        expected.queue(MoveToColumn(0))?;
+        expected.queue(Clear(ClearType::FromCursorDown))?;
+        expected.queue(Show)?;
+        Ok(expected)
+    }
+
+    #[test]
+    fn test_restore_clears_canvas() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut console = console(&writer, |builder| {
+            builder.restore_on_panic();
+        });
+        console.render(&canvas()?)?;
+
+        console.restore.as_ref().unwrap().restore(None);
+        assert!(writer.0.lock().unwrap().ends_with(&cleared(2)?));
+
+        // The console starts over below, without moving up over what was printed since.
+        let written = writer.0.lock().unwrap().len();
+        console.emit(Lines(vec![vec!["after"].try_into()?]));
+        console.render(&canvas()?)?;
+        let output = writer.0.lock().unwrap();
+        let frame = &output[written..];
+        assert!(frame_contains(frame, "after"));
+        assert!(frame_contains(frame, "line 2"));
+        assert!(!frame_contains(frame, {
+            let mut up = Vec::new();
+            up.queue(MoveUp(1))?;
+            up
+        }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_leaves_alternate_screen() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut console = console(&writer, |builder| {
+            builder.alternate_screen().restore_on_panic();
+        });
+        console.render(&canvas()?)?;
+
+        console.restore.as_ref().unwrap().restore(None);
+        let mut expected = Vec::new();
+        expected.queue(LeaveAlternateScreen)?;
+        expected.queue(Show)?;
+        assert!(writer.0.lock().unwrap().ends_with(&expected));
+
+        Ok(())
+    }
+
+    struct Panics;
+
+    impl Component for Panics {
+        type Error = Infallible;
+
+        fn draw_unchecked(
+            &self,
+            _dimensions: Dimensions,
+            _mode: DrawMode,
+        ) -> Result<Lines, Infallible> {
+            panic!("drawing failed")
+        }
+    }
+
+    #[test]
+    fn test_panic_hook() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut console = console(&writer, |builder| {
+            builder.non_blocking().restore_on_panic();
+        });
+
+        let res = std::thread::spawn(move || {
+            console.render(&canvas().unwrap()).unwrap();
+            // Unlike renders, finalizing always draws.
+            console.finalize(&Panics)
+        })
+        .join();
+        assert!(res.is_err());
+
+        // The output thread wrote the canvas before it was cleared.
+        let output = writer.0.lock().unwrap();
+        assert!(frame_contains(&output, "line 2"));
+        assert!(output.ends_with(&cleared(2)?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_panic_hook_ignores_other_threads() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut console = console(&writer, |builder| {
+            builder.restore_on_panic();
+        });
+        console.render(&canvas()?)?;
+
+        let written = writer.0.lock().unwrap().len();
+        assert!(std::thread::spawn(|| panic!("unrelated")).join().is_err());
+        assert_eq!(writer.0.lock().unwrap().len(), written);
+
+        // A caught panic on the thread drawing the console clears it, until the next render.
+        assert!(std::panic::catch_unwind(|| panic!("caught")).is_err());
+        assert!(writer.0.lock().unwrap().ends_with(&cleared(2)?));
+        console.render(&canvas()?)?;
+        let output = writer.0.lock().unwrap();
+        assert!(frame_contains(&output[written..], "line 2"));
+
+        Ok(())
+    }
+
+    /// Blocks writes while the test holds the lock.
+    struct Stuck(Arc<Mutex<()>>);
+
+    impl std::io::Write for Stuck {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let _guard = lock(&self.0);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_restore_gives_up_on_stuck_output() -> anyhow::Result<()> {
+        let gate = Arc::new(Mutex::new(()));
+        let mut builder = Builder::new();
+        builder
+            .write_to(Box::new(Stuck(gate.clone())))
+            .non_blocking()
+            .restore_on_panic();
+        let mut console = builder.build_forced(Dimensions::new(80, 24))?;
+        console.render(&canvas()?)?;
+
+        let held = lock(&gate);
+        let start = Instant::now();
+        console.render(&canvas()?)?;
+        console.restore.as_ref().unwrap().restore(None);
+        assert!(start.elapsed() < FLUSH_TIMEOUT * 5);
+        drop(held);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Counters of the work done by a [`SuperConsole`](crate::SuperConsole), for telemetry.
+
+use std::time::Duration;
+
+/// Totals since the console was created, returned by
+/// [`SuperConsole::stats`](crate::SuperConsole::stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of times the root component was drawn.
+    pub frames_rendered: u64,
+    /// Number of renders skipped because the output was not ready for another frame.
+    pub frames_skipped: u64,
+    /// Bytes written to the main output.
+    pub main_bytes: u64,
+    /// Bytes written to the auxillary output.
+    pub aux_bytes: u64,
+    /// Emitted lines written out, after wrapping if enabled.
+    pub lines_emitted: u64,
+    /// Emitted lines dropped by the [`OverflowPolicy`](crate::builder::OverflowPolicy).
+    pub lines_elided: u64,
+    /// Total time spent drawing the root component.
+    pub draw_time: Duration,
+    /// Longest time spent drawing the root component at once.
+    pub max_draw_time: Duration,
+    /// Total time spent handing frames to the output. For non-blocking outputs, this is the time
+    /// to queue them rather than to write them.
+    pub write_time: Duration,
+    /// Longest time spent handing a single frame to the output.
+    pub max_write_time: Duration,
+}
+
+impl Stats {
+    pub(crate) fn record_draw(&mut self, time: Duration) {
+        self.frames_rendered += 1;
+        self.draw_time += time;
+        self.max_draw_time = self.max_draw_time.max(time);
+    }
+
+    pub(crate) fn record_write(&mut self, time: Duration) {
+        self.write_time += time;
+        self.max_write_time = self.max_write_time.max(time);
+    }
+}
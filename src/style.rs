@@ -19,3 +19,233 @@ pub use crossterm::style::ContentStyle;
 pub use crossterm::style::StyledContent;
 pub use crossterm::style::Stylize;
 pub use crossterm::style::style;
+
+/// How many colors the terminal can display. Colors beyond that are mapped to the nearest one
+/// the terminal supports when rendering, or stripped. Attributes are kept either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    /// No colors at all.
+    NoColor,
+    /// The 16 named ANSI colors.
+    Ansi16,
+    /// The 256 colors of the xterm palette.
+    Ansi256,
+    /// 24-bit RGB colors.
+    #[default]
+    TrueColor,
+}
+
+/// The default xterm values of the 16 named colors, by palette index.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The levels of each component in the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorSupport {
+    /// Detects what the terminal supports from the environment: `NO_COLOR` disables colors,
+    /// `COLORTERM` and `TERM` tell how many are supported, and `CLICOLOR_FORCE` enables at least
+    /// the 16 named colors where `TERM` would disable them.
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |name| var(name).is_some_and(|value| !value.is_empty());
+        if set("NO_COLOR") {
+            return Self::NoColor;
+        }
+        let forced = var("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0");
+        let least = if forced { Self::Ansi16 } else { Self::NoColor };
+
+        if let Some(colorterm) = var("COLORTERM")
+            && (colorterm == "truecolor" || colorterm == "24bit")
+        {
+            return Self::TrueColor;
+        }
+        let detected = match var("TERM").as_deref() {
+            None if cfg!(windows) => Self::TrueColor,
+            None | Some("" | "dumb") => Self::NoColor,
+            Some(term) if term.contains("truecolor") || term.contains("direct") => Self::TrueColor,
+            Some(term) if term.contains("256color") => Self::Ansi256,
+            Some(_) => Self::Ansi16,
+        };
+        detected.max(least)
+    }
+
+    /// The color to render in place of `color`, or `None` if it should not be rendered at all.
+    pub fn downgrade(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (Self::NoColor, _) => None,
+            (Self::TrueColor, _) => Some(color),
+            (Self::Ansi256, Color::Rgb { r, g, b }) => {
+                Some(Color::AnsiValue(nearest_256((r, g, b))))
+            }
+            (Self::Ansi16, Color::Rgb { r, g, b }) => Some(nearest_16((r, g, b))),
+            (Self::Ansi16, Color::AnsiValue(index)) => Some(match ANSI16.get(index as usize) {
+                Some((named, _)) => *named,
+                None => nearest_16(palette_rgb(index)),
+            }),
+            _ => Some(color),
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).unsigned_abs().pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, value)| distance(rgb, *value))
+        .map(|(color, _)| *color)
+        .expect("not empty")
+}
+
+/// The closest color of the 6x6x6 cube or of the grayscale ramp of the 256-color palette.
+fn nearest_256(rgb @ (r, g, b): (u8, u8, u8)) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .expect("not empty") as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(rgb, palette_rgb(gray)) < distance(rgb, palette_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// The default xterm value of a color of the 256-color palette.
+fn palette_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..16 => ANSI16[index as usize].1,
+        16..232 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        232.. => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> ColorSupport {
+        ColorSupport::from_env(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (*value).to_owned())
+        })
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(env(&[("TERM", "xterm")]), ColorSupport::Ansi16);
+        assert_eq!(env(&[("TERM", "xterm-256color")]), ColorSupport::Ansi256);
+        assert_eq!(
+            env(&[("TERM", "xterm-256color"), ("COLORTERM", "truecolor")]),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(env(&[("TERM", "dumb")]), ColorSupport::NoColor);
+        assert_eq!(
+            env(&[("TERM", "dumb"), ("CLICOLOR_FORCE", "1")]),
+            ColorSupport::Ansi16
+        );
+        assert_eq!(
+            env(&[("TERM", "dumb"), ("CLICOLOR_FORCE", "0")]),
+            ColorSupport::NoColor
+        );
+        assert_eq!(
+            env(&[
+                ("TERM", "xterm-256color"),
+                ("NO_COLOR", "1"),
+                ("CLICOLOR_FORCE", "1")
+            ]),
+            ColorSupport::NoColor
+        );
+        assert_eq!(
+            env(&[("TERM", "xterm-256color"), ("NO_COLOR", "")]),
+            ColorSupport::Ansi256
+        );
+    }
+
+    #[test]
+    fn test_downgrade() {
+        let orange = Color::Rgb {
+            r: 255,
+            g: 135,
+            b: 0,
+        };
+        let gray = Color::Rgb {
+            r: 100,
+            g: 100,
+            b: 102,
+        };
+        assert_eq!(ColorSupport::TrueColor.downgrade(orange), Some(orange));
+        assert_eq!(
+            ColorSupport::Ansi256.downgrade(orange),
+            Some(Color::AnsiValue(208))
+        );
+        assert_eq!(
+            ColorSupport::Ansi256.downgrade(gray),
+            Some(Color::AnsiValue(241))
+        );
+        assert_eq!(
+            ColorSupport::Ansi16.downgrade(orange),
+            Some(Color::DarkYellow)
+        );
+        assert_eq!(ColorSupport::Ansi16.downgrade(gray), Some(Color::DarkGrey));
+        assert_eq!(
+            ColorSupport::Ansi16.downgrade(Color::AnsiValue(9)),
+            Some(Color::Red)
+        );
+        assert_eq!(
+            ColorSupport::Ansi16.downgrade(Color::AnsiValue(21)),
+            Some(Color::DarkBlue)
+        );
+        assert_eq!(
+            ColorSupport::Ansi256.downgrade(Color::Red),
+            Some(Color::Red)
+        );
+        assert_eq!(ColorSupport::NoColor.downgrade(Color::Red), None);
+    }
+
+    #[test]
+    fn test_palette_round_trip() {
+        for index in 16..=255 {
+            assert_eq!(nearest_256(palette_rgb(index)), index, "{index}");
+        }
+    }
+}
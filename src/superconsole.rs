@@ -11,29 +11,114 @@
 use std::cmp;
 use std::env;
 use std::io;
-
+use std::io::Write;
+use std::sync::Arc;
+#[cfg(unix)]
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::sync::MutexGuard;
+#[cfg(unix)]
+use std::sync::PoisonError;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use crossbeam_channel::Receiver;
+use crossbeam_channel::Sender;
 use crossterm::QueueableCommand;
+use crossterm::cursor::Hide;
+use crossterm::cursor::MoveTo;
 use crossterm::cursor::MoveToColumn;
 use crossterm::cursor::MoveUp;
+use crossterm::cursor::Show;
+use crossterm::terminal::BeginSynchronizedUpdate;
 use crossterm::terminal::Clear;
 use crossterm::terminal::ClearType;
+use crossterm::terminal::EndSynchronizedUpdate;
+use crossterm::terminal::EnterAlternateScreen;
+use crossterm::terminal::LeaveAlternateScreen;
 use crossterm::tty::IsTty;
 
 use crate::Dimensions;
 use crate::Direction;
 use crate::Lines;
+use crate::Span;
 use crate::ansi_support::enable_ansi_support;
+use crate::builder::CanvasHeight;
+use crate::builder::CanvasSnapshots;
+use crate::builder::OverflowPolicy;
+#[cfg(unix)]
+use crate::capture::PausedCapture;
+#[cfg(unix)]
+use crate::capture::StdioCapture;
 use crate::components::Component;
 use crate::components::DrawMode;
 use crate::content::Line;
+use crate::emitter::Emitted;
+use crate::emitter::Emitter;
 use crate::error::OutputError;
 use crate::output::BlockingSuperConsoleOutput;
 use crate::output::OutputTarget;
 use crate::output::SuperConsoleOutput;
+use crate::restore::Restore;
+use crate::stats::Stats;
+use crate::style::ColorSupport;
+use crate::style::StyledContent;
+use crate::theme::Theme;
 
 const MINIMUM_EMIT: usize = 5;
 const MAX_GRAPHEME_BUFFER: usize = 1000000;
 
+/// Options controlling how a [`SuperConsole`] renders, set through the [`Builder`](crate::Builder).
+#[derive(Clone, Debug)]
+pub(crate) struct SuperConsoleConfig {
+    /// Wrap each frame in the synchronized update sequences (DEC mode 2026), so that terminals
+    /// which support it never paint a partially written frame.
+    pub(crate) synchronized_output: bool,
+    /// Maximum number of messages queued by [`Emitter`]s between two renders, unbounded if `None`.
+    pub(crate) emitter_capacity: Option<usize>,
+    /// Draw the canvas full-screen on the alternate screen, holding back emitted lines until the
+    /// primary screen is restored.
+    pub(crate) alternate_screen: bool,
+    /// The most rows the canvas may take, out of those the terminal has left for it.
+    pub(crate) max_canvas_height: Option<CanvasHeight>,
+    /// The least number of emitted lines drawn per frame.
+    pub(crate) minimum_emit: usize,
+    /// How many graphemes of emitted lines may be buffered before `overflow` applies.
+    pub(crate) max_buffered_graphemes: usize,
+    pub(crate) overflow: OverflowPolicy,
+    /// Hard-wrap emitted lines to the terminal width, instead of letting the terminal wrap them.
+    pub(crate) wrap_emitted_lines: bool,
+    /// Hide the cursor while the canvas is displayed.
+    pub(crate) hide_cursor: bool,
+    /// The colors the terminal supports, which colors are downgraded to.
+    pub(crate) color_support: ColorSupport,
+    /// The theme current while drawing the root component.
+    pub(crate) theme: Theme,
+    /// Write plain text without escape codes, printing snapshots of the canvas as configured,
+    /// for outputs that aren't terminals.
+    pub(crate) plain: Option<CanvasSnapshots>,
+}
+
+impl Default for SuperConsoleConfig {
+    fn default() -> Self {
+        Self {
+            synchronized_output: true,
+            emitter_capacity: None,
+            alternate_screen: false,
+            max_canvas_height: None,
+            minimum_emit: MINIMUM_EMIT,
+            max_buffered_graphemes: MAX_GRAPHEME_BUFFER,
+            overflow: OverflowPolicy::default(),
+            wrap_emitted_lines: false,
+            hide_cursor: true,
+            color_support: ColorSupport::TrueColor,
+            theme: Theme::plain(),
+            plain: None,
+        }
+    }
+}
+
 /// Handles rendering the console using the user-defined [Component](Component)s and emitted messages.
 /// A Canvas area at the bottom of the terminal is re-rendered in place at each tick for the components,
 /// while a log area of emitted messages is produced above.
@@ -41,17 +126,43 @@ const MAX_GRAPHEME_BUFFER: usize = 1000000;
 pub struct SuperConsole {
     /// Number of lines that were used to render the canvas last time.
     canvas_contents: Lines,
+    /// The size of the terminal when the canvas was rendered last time.
+    canvas_size: Dimensions,
     /// Buffer storing the lines (stderr by default) we should emit next time we render.
     to_emit: Lines,
     // Buffer storing auxillary output (stdio by default) that should be emitted next time we render.
     aux_to_emit: Lines,
+    /// Queue of lines emitted through [`Emitter`]s, drained into the buffers above on each render.
+    emitted_sender: Sender<Emitted>,
+    emitted_receiver: Receiver<Emitted>,
     /// A default screen size to use if the size cannot be fetched
     /// from the terminal. This generally is only used for testing
     /// situations.
     fallback_size: Option<Dimensions>,
+    config: SuperConsoleConfig,
+    /// Whether the canvas is currently drawn on the alternate screen.
+    in_alternate_screen: bool,
+    /// Whether the cursor was hidden, and has to be shown again.
+    cursor_hidden: bool,
+    /// When the last snapshot of the canvas was printed in plain mode.
+    last_snapshot: Option<Instant>,
+    /// Set while a [`SuspendGuard`] is alive.
+    suspended: Arc<AtomicBool>,
+    stats: Stats,
+    /// How many dropped lines the marker at the front of `to_emit` stands for, or 0 if there is no
+    /// marker waiting to be drawn.
+    elided: usize,
+    /// Redirection of stdout and stderr, emitting what is written to them.
+    #[cfg(unix)]
+    pub(crate) capture: Option<StdioCapture>,
+    /// Set while a [`SuspendGuard`] keeps stdout and stderr pointed at the original files.
+    #[cfg(unix)]
+    paused_capture: Arc<Mutex<Option<PausedCapture>>>,
+    /// Shared with the panic hook and signal handler, if they should restore the terminal.
+    pub(crate) restore: Option<Arc<Restore>>,
     /// The terminal handle to write a buffer to the screen.
-    /// All IO goes through this handle.
-    pub(crate) output: Box<dyn SuperConsoleOutput>,
+    /// All IO goes through this handle. Only taken by `finalize`.
+    pub(crate) output: Option<Box<dyn SuperConsoleOutput>>,
 }
 
 impl SuperConsole {
@@ -84,11 +195,42 @@ impl SuperConsole {
         fallback_size: Option<Dimensions>,
         output: Box<dyn SuperConsoleOutput>,
     ) -> Self {
+        let config = SuperConsoleConfig {
+            color_support: ColorSupport::detect(),
+            ..Default::default()
+        };
+        Self::new_with_config(fallback_size, output, config)
+    }
+
+    pub(crate) fn new_with_config(
+        fallback_size: Option<Dimensions>,
+        output: Box<dyn SuperConsoleOutput>,
+        config: SuperConsoleConfig,
+    ) -> Self {
+        let (emitted_sender, emitted_receiver) = match config.emitter_capacity {
+            Some(capacity) => crossbeam_channel::bounded(capacity),
+            None => crossbeam_channel::unbounded(),
+        };
         Self {
             canvas_contents: Lines::new(),
+            canvas_size: Dimensions::default(),
             to_emit: Lines::new(),
+            emitted_sender,
+            emitted_receiver,
             fallback_size,
-            output,
+            config,
+            in_alternate_screen: false,
+            cursor_hidden: false,
+            last_snapshot: None,
+            suspended: Arc::new(AtomicBool::new(false)),
+            stats: Stats::default(),
+            elided: 0,
+            #[cfg(unix)]
+            capture: None,
+            #[cfg(unix)]
+            paused_capture: Arc::new(Mutex::new(None)),
+            restore: None,
+            output: Some(output),
             aux_to_emit: Lines::new(),
         }
     }
@@ -108,6 +250,18 @@ impl SuperConsole {
     /// Render at a given tick.  Draws all components and drains the emitted events buffer.
     /// This will produce any pending emitting events above the Canvas and will re-render the drawing area.
     pub fn render<C: Component + ?Sized>(&mut self, root: &C) -> crate::RenderResult<(), C> {
+        self.forget_restored_screen();
+        self.drain_emitted(DrawMode::Normal);
+
+        if self.config.alternate_screen {
+            if self.should_render() {
+                self.render_alternate_screen(root)?;
+            } else {
+                self.stats.frames_skipped += 1;
+            }
+            return Ok(());
+        }
+
         // `render_general` refuses to drain more than a single frame, so repeat until done.
         // or until the rendered frame is too large to print anything.
         let mut anything_emitted = true;
@@ -115,7 +269,10 @@ impl SuperConsole {
         while !has_rendered
             || (anything_emitted && !(self.to_emit.is_empty() && self.aux_to_emit.is_empty()))
         {
-            if !self.output.should_render() {
+            if !self.should_render() {
+                if !has_rendered {
+                    self.stats.frames_skipped += 1;
+                }
                 break;
             }
 
@@ -138,13 +295,28 @@ impl SuperConsole {
 
     /// Perform a final render, using a specified [`DrawMode`].
     /// Each component will have a chance to finalize themselves before the terminal is disposed of.
+    ///
+    /// If the canvas was drawn on the alternate screen, the primary screen is restored first, and
+    /// the held back lines are emitted above the final canvas.
+    ///
+    /// Finalizing ends a suspension: the final canvas is drawn whether or not a [`SuspendGuard`]
+    /// is alive, and dropping the guard has no effect afterwards.
     pub fn finalize_with_mode<C: Component + ?Sized>(
         mut self,
         root: &C,
         mode: DrawMode,
     ) -> crate::RenderResult<(), C> {
+        self.forget_restored_screen();
+        self.stop_capture();
+        self.drain_emitted(DrawMode::Final);
+        self.leave_alternate_screen()?;
         self.render_with_mode(root, mode)?;
-        self.output.finalize().map_err(Into::into)
+        self.show_cursor()?;
+        self.output
+            .take()
+            .expect("output is only taken by finalize")
+            .finalize()
+            .map_err(Into::into)
     }
 
     /// Convenience method:
@@ -164,27 +336,185 @@ impl SuperConsole {
 
     /// Queues the passed lines to be drawn on the next render.
     /// The lines *will not* appear until the next render is called.
-    pub fn emit(&mut self, lines: Lines) {
+    pub fn emit(&mut self, mut lines: Lines) {
+        lines.downgrade_colors(self.config.color_support);
         self.to_emit.extend(lines);
     }
 
     /// Queues the passed lines of auxillary output to be drawn on the next render.
     /// The lines *will not* appear until the next render is called.
-    pub fn emit_aux(&mut self, lines: Lines) {
+    pub fn emit_aux(&mut self, mut lines: Lines) {
+        lines.downgrade_colors(self.config.color_support);
         self.aux_to_emit.extend(lines);
     }
 
+    /// Returns a handle to queue lines from other threads, without access to the console.
+    /// They are picked up on the next render.
+    pub fn emitter(&self) -> Emitter {
+        Emitter {
+            sender: self.emitted_sender.clone(),
+            theme: self.config.theme,
+        }
+    }
+
+    /// Clears the canvas and writes out every pending emitted line, waits for the output to be
+    /// flushed, then stops rendering until the returned guard is dropped. Use this before running
+    /// an interactive child process, like an editor.
+    ///
+    /// Lines emitted in the meantime are kept for later. The canvas is drawn again, below whatever
+    /// the child process printed, by the first render after the guard is dropped.
+    ///
+    /// If stdout and stderr are captured, they point at the terminal again until the guard is
+    /// dropped, so that the child process can use it directly.
+    pub fn suspend(&mut self) -> Result<SuspendGuard, OutputError> {
+        self.forget_restored_screen();
+        self.drain_emitted(DrawMode::Final);
+        self.leave_alternate_screen()?;
+        if self.config.plain.is_some() {
+            self.write_plain(None)?;
+        } else {
+            let size = self.size()?.saturating_sub(1, Direction::Vertical);
+            self.render_canvas(Lines::new(), DrawMode::Final, size)?;
+        }
+        self.show_cursor()?;
+        self.output().flush()?;
+
+        #[cfg(unix)]
+        if let Some(capture) = &self.capture {
+            let paused = capture.pause()?;
+            *lock(&self.paused_capture) = Some(paused);
+        }
+        self.suspended.store(true, Ordering::Release);
+        Ok(SuspendGuard {
+            suspended: self.suspended.clone(),
+            #[cfg(unix)]
+            paused_capture: self.paused_capture.clone(),
+        })
+    }
+
+    /// Counters of the work done since the console was created.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// Number of rows the canvas occupied when it was last drawn.
+    pub(crate) fn canvas_height(&self) -> usize {
+        self.canvas_contents.len()
+    }
+
+    /// Whether a [`SuspendGuard`] is alive.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.load(Ordering::Acquire)
+    }
+
+    /// Whether to draw a frame now: not while suspended, nor while the output is busy.
+    fn should_render(&mut self) -> bool {
+        !self.is_suspended() && self.output().should_render()
+    }
+
+    /// Restores stdout and stderr if they were captured.
+    fn stop_capture(&mut self) {
+        #[cfg(unix)]
+        {
+            // Stdout and stderr already point at the original files, keep them there.
+            lock(&self.paused_capture).take();
+            if let Some(capture) = self.capture.take() {
+                capture.stop();
+            }
+        }
+    }
+
+    /// Moves the lines queued by [`Emitter`]s to the buffers of lines to emit.
+    /// With [`OverflowPolicy::Block`], normal renders leave them queued while the buffers are full.
+    fn drain_emitted(&mut self, mode: DrawMode) {
+        let mut room = match (mode, self.config.overflow) {
+            (DrawMode::Normal, OverflowPolicy::Block) => self
+                .config
+                .max_buffered_graphemes
+                .saturating_sub(self.buffered_graphemes()),
+            _ => usize::MAX,
+        };
+        // Only take what is already queued, so that busy emitters can't keep us here forever.
+        for _ in 0..self.emitted_receiver.len() {
+            if room == 0 {
+                break;
+            }
+            let (buffer, mut lines) = match self.emitted_receiver.try_recv() {
+                Ok(Emitted::Main(lines)) => (&mut self.to_emit, lines),
+                Ok(Emitted::Aux(lines)) => (&mut self.aux_to_emit, lines),
+                Err(_) => break,
+            };
+            room = room.saturating_sub(lines.iter().map(Line::len).sum());
+            lines.downgrade_colors(self.config.color_support);
+            buffer.extend(lines);
+        }
+    }
+
+    /// The number of graphemes in the emitted lines waiting to be drawn.
+    fn buffered_graphemes(&self) -> usize {
+        self.to_emit
+            .iter()
+            .chain(self.aux_to_emit.iter())
+            .map(Line::len)
+            .sum()
+    }
+
+    /// Drops the oldest regular lines until the buffers fit in `max_buffered_graphemes`, drawing
+    /// a marker in their place with [`OverflowPolicy::Collapse`]. A marker that wasn't drawn yet
+    /// is replaced by one counting its lines too.
+    fn drop_oldest_emitted(&mut self) {
+        let previously_elided = std::mem::take(&mut self.elided);
+        if previously_elided > 0 {
+            self.to_emit.0.remove(0);
+        }
+        let aux: usize = self.aux_to_emit.iter().map(Line::len).sum();
+        let room = self.config.max_buffered_graphemes.saturating_sub(aux);
+        let mut kept_graphemes = 0;
+        let kept = self
+            .to_emit
+            .0
+            .iter()
+            .rev()
+            .take_while(|line| {
+                kept_graphemes += line.len();
+                kept_graphemes <= room
+            })
+            .count();
+        let dropped = self.to_emit.len() - kept;
+        self.to_emit.0.drain(..dropped);
+        self.stats.lines_elided += dropped as u64;
+        let elided = previously_elided + dropped;
+        if self.config.overflow == OverflowPolicy::Collapse && elided > 0 {
+            self.to_emit.0.insert(0, self.elided_marker(elided));
+            self.elided = elided;
+        }
+    }
+
+    /// The line drawn in place of `count` dropped lines.
+    fn elided_marker(&self, count: usize) -> Line {
+        let mut marker = Line::from_iter([Span::new_styled_lossy(StyledContent::new(
+            self.config.theme.dim,
+            format!("… {count} lines elided"),
+        ))]);
+        marker.downgrade_colors(self.config.color_support);
+        marker
+    }
+
     fn size(&self) -> Result<Dimensions, OutputError> {
-        if let Ok(width) = std::env::var("SUPERCONSOLE_TESTING_WIDTH") {
-            let width: usize = width.parse().unwrap();
-            let height = std::env::var("SUPERCONSOLE_TESTING_HEIGHT")
-                .unwrap()
-                .parse()
-                .unwrap();
-            return Ok(Dimensions::new(width, height));
+        Self::resolve_size(self.fallback_size, || self.output_ref().terminal_size())
+    }
+
+    /// The size to draw at: forced by the environment, or else the size of the terminal unless
+    /// that fails or is empty and there is a `fallback_size`.
+    pub(crate) fn resolve_size(
+        fallback_size: Option<Dimensions>,
+        terminal_size: impl FnOnce() -> Result<Dimensions, OutputError>,
+    ) -> Result<Dimensions, OutputError> {
+        if let Some(size) = Self::size_from_env() {
+            return Ok(size);
         }
         // We want to get the size, but if that fails or is empty use the fallback_size if available.
-        match (self.output.terminal_size(), self.fallback_size) {
+        match (terminal_size(), fallback_size) {
             (Ok(size), Some(fallback)) if size.width == 0 || size.height == 0 => Ok(fallback),
             (Ok(size), _) => Ok(size),
             (Err(_), Some(fallback)) => Ok(fallback),
@@ -192,6 +522,16 @@ impl SuperConsole {
         }
     }
 
+    /// The size forced by `SUPERCONSOLE_TESTING_WIDTH` and `SUPERCONSOLE_TESTING_HEIGHT`, for tests
+    /// of programs using the console. Ignored unless both are set to numbers.
+    fn size_from_env() -> Option<Dimensions> {
+        let var = |name| std::env::var(name).ok()?.parse().ok();
+        Some(Dimensions::new(
+            var("SUPERCONSOLE_TESTING_WIDTH")?,
+            var("SUPERCONSOLE_TESTING_HEIGHT")?,
+        ))
+    }
+
     /// The first step of drawing.  It moves the buffer up to be overwritten and sets the length to 0.
     /// This is used to clear the scratch area so that any possibly emitted messages can write over it.
     pub(crate) fn clear_canvas_pre(
@@ -219,13 +559,223 @@ impl SuperConsole {
         Ok(())
     }
 
-    /// Clears the canvas portion of the superconsole.
+    /// Clears the canvas portion of the superconsole. Does nothing while suspended, since the
+    /// canvas was cleared by [`suspend`](SuperConsole::suspend).
     pub fn clear(&mut self) -> Result<(), OutputError> {
+        if self.is_suspended() {
+            return Ok(());
+        }
+        self.forget_restored_screen();
+        if self.config.plain.is_some() {
+            // Snapshots are never overwritten.
+            self.canvas_contents = Lines::new();
+            return Ok(());
+        }
         let mut buffer = Vec::new();
-        Self::clear_canvas_pre(&mut buffer, self.canvas_contents.len())?;
+        if self.in_alternate_screen {
+            buffer.queue(MoveTo(0, 0)).map_err(OutputError::Terminal)?;
+        } else {
+            let rows = match self.size() {
+                Ok(size) => self.canvas_rows(size.width),
+                Err(_) => self.canvas_contents.len(),
+            };
+            Self::clear_canvas_pre(&mut buffer, rows)?;
+        }
         self.canvas_contents = Lines::new();
         Self::clear_canvas_post(&mut buffer)?;
-        self.output.output(buffer).map_err(Into::into)
+        self.queue_show_cursor(&mut buffer)?;
+        self.output_frame(buffer)
+    }
+
+    /// The number of rows the canvas occupies on the terminal once it is `width` columns wide.
+    /// When the terminal got narrower, the terminal wrapped the lines of the canvas that no longer
+    /// fit onto additional rows.
+    fn canvas_rows(&self, width: usize) -> usize {
+        if width == 0 || width >= self.canvas_size.width {
+            return self.canvas_contents.len();
+        }
+        self.canvas_contents
+            .iter()
+            .map(|line| line.rows(width))
+            .sum()
+    }
+
+    fn output(&mut self) -> &mut dyn SuperConsoleOutput {
+        self.output
+            .as_deref_mut()
+            .expect("output is only taken by finalize")
+    }
+
+    fn output_ref(&self) -> &dyn SuperConsoleOutput {
+        self.output
+            .as_deref()
+            .expect("output is only taken by finalize")
+    }
+
+    /// Draws the root component, trimmed to `size` as we don't trust it to do so.
+    fn draw_root<C: Component + ?Sized>(
+        &mut self,
+        root: &C,
+        size: Dimensions,
+        mode: DrawMode,
+    ) -> crate::RenderResult<Lines, C> {
+        let start = Instant::now();
+        let mut canvas = self
+            .config
+            .theme
+            .scope(|| root.draw(size, mode))
+            .map_err(crate::Error::Draw)?;
+        canvas.shrink_lines_to_dimensions(size);
+        canvas.downgrade_colors(self.config.color_support);
+        self.stats.record_draw(start.elapsed());
+        Ok(canvas)
+    }
+
+    /// Hands `buffer` to the output.
+    fn write(&mut self, buffer: Vec<u8>, target: OutputTarget) -> Result<(), OutputError> {
+        let len = buffer.len() as u64;
+        let start = Instant::now();
+        match target {
+            OutputTarget::Main => self.output().output(buffer)?,
+            OutputTarget::Aux => self.output().output_to(buffer, target)?,
+        }
+        self.stats.record_write(start.elapsed());
+        match target {
+            OutputTarget::Main => self.stats.main_bytes += len,
+            OutputTarget::Aux => self.stats.aux_bytes += len,
+        }
+        Ok(())
+    }
+
+    /// Sends a frame to the main output, bracketed by synchronized update sequences if enabled.
+    fn output_frame(&mut self, buffer: Vec<u8>) -> Result<(), OutputError> {
+        if self.config.synchronized_output {
+            let mut frame = Vec::with_capacity(buffer.len() + 16);
+            frame
+                .queue(BeginSynchronizedUpdate)
+                .map_err(OutputError::Terminal)?;
+            frame.extend(buffer);
+            frame
+                .queue(EndSynchronizedUpdate)
+                .map_err(OutputError::Terminal)?;
+            self.write(frame, OutputTarget::Main)?;
+        } else {
+            self.write(buffer, OutputTarget::Main)?;
+        }
+        self.publish_canvas();
+        Ok(())
+    }
+
+    /// Starts over below whatever was printed since, if the panic hook or the signal handler
+    /// restored the terminal.
+    fn forget_restored_screen(&mut self) {
+        if let Some(restore) = &self.restore
+            && restore.take_restored()
+        {
+            self.canvas_contents = Lines::new();
+            self.in_alternate_screen = false;
+            self.cursor_hidden = false;
+        }
+    }
+
+    /// Tells the panic hook and signal handler what they have to clear.
+    fn publish_canvas(&self) {
+        if let Some(restore) = &self.restore {
+            restore.set_screen(
+                self.canvas_contents.len(),
+                self.in_alternate_screen,
+                self.cursor_hidden,
+            );
+        }
+    }
+
+    /// Hides the cursor at the start of a frame drawing the canvas, if enabled.
+    fn queue_hide_cursor(&mut self, buffer: &mut Vec<u8>) -> Result<(), OutputError> {
+        if self.config.hide_cursor && !self.cursor_hidden {
+            buffer.queue(Hide).map_err(OutputError::Terminal)?;
+            self.cursor_hidden = true;
+        }
+        Ok(())
+    }
+
+    /// Shows the cursor again at the end of a frame, if it was hidden.
+    fn queue_show_cursor(&mut self, buffer: &mut Vec<u8>) -> Result<(), OutputError> {
+        if self.cursor_hidden {
+            buffer.queue(Show).map_err(OutputError::Terminal)?;
+            self.cursor_hidden = false;
+        }
+        Ok(())
+    }
+
+    /// Shows the cursor again, if it was hidden.
+    fn show_cursor(&mut self) -> Result<(), OutputError> {
+        let mut buffer = Vec::new();
+        self.queue_show_cursor(&mut buffer)?;
+        if !buffer.is_empty() {
+            self.write(buffer, OutputTarget::Main)?;
+            self.publish_canvas();
+        }
+        Ok(())
+    }
+
+    /// Draws the canvas over the whole alternate screen, entering it first if needed.
+    /// Emitted lines are held back until the primary screen is restored, except for auxillary
+    /// output that doesn't go to a tty.
+    fn render_alternate_screen<C: Component + ?Sized>(
+        &mut self,
+        root: &C,
+    ) -> crate::RenderResult<(), C> {
+        // There is no blank final line to keep here, so the root gets the whole screen.
+        let size = self.size()?;
+        let canvas = self.draw_root(root, size, DrawMode::Normal)?;
+
+        if !self.aux_to_emit.is_empty() && !self.output().aux_stream_is_tty() {
+            self.stats.lines_emitted += self.aux_to_emit.len() as u64;
+            let mut aux_buffer = Vec::new();
+            self.aux_to_emit.render_raw(&mut aux_buffer);
+            self.write(aux_buffer, OutputTarget::Aux)?;
+        }
+
+        let mut buffer = Vec::new();
+        self.queue_hide_cursor(&mut buffer)?;
+        let entering = !self.in_alternate_screen;
+        if entering {
+            buffer
+                .queue(EnterAlternateScreen)
+                .map_err(OutputError::Terminal)?;
+            self.in_alternate_screen = true;
+        }
+        if entering || self.canvas_size != size {
+            // Whatever the terminal made of the previous canvas when resizing, start over.
+            buffer.queue(MoveTo(0, 0)).map_err(OutputError::Terminal)?;
+            buffer
+                .queue(Clear(ClearType::All))
+                .map_err(OutputError::Terminal)?;
+            self.canvas_contents = Lines::new();
+        }
+        canvas.render_diff_absolute(&mut buffer, &self.canvas_contents);
+        self.canvas_contents = canvas;
+        self.canvas_size = size;
+        self.output_frame(buffer)?;
+
+        Ok(())
+    }
+
+    /// Restores the primary screen if the canvas is on the alternate screen. The canvas is then
+    /// drawn from scratch by the next render, below the cursor position from before entering.
+    fn leave_alternate_screen(&mut self) -> Result<(), OutputError> {
+        if !self.in_alternate_screen {
+            return Ok(());
+        }
+        let mut buffer = Vec::new();
+        buffer
+            .queue(LeaveAlternateScreen)
+            .map_err(OutputError::Terminal)?;
+        self.write(buffer, OutputTarget::Main)?;
+        self.canvas_contents = Lines::new();
+        self.in_alternate_screen = false;
+        self.publish_canvas();
+        Ok(())
     }
 
     /// Helper method to share render + finalize behavior by specifying mode.
@@ -234,9 +784,9 @@ impl SuperConsole {
         root: &C,
         mode: DrawMode,
     ) -> crate::RenderResult<(), C> {
-        // TODO(cjhopman): We may need to try to keep each write call to be under the pipe buffer
-        // size so it can be completed in a single syscall otherwise we might see a partially
-        // rendered frame.
+        if let Some(snapshots) = self.config.plain {
+            return self.render_plain(root, mode, snapshots);
+        }
 
         // We remove the last line as we always have a blank final line in our output.
         let size = self.size()?.saturating_sub(1, Direction::Vertical);
@@ -246,6 +796,62 @@ impl SuperConsole {
         Ok(())
     }
 
+    /// Renders without escape codes: emitted lines are printed as plain text, followed by a
+    /// snapshot of the canvas when `snapshots` asks for one, and always on the final render.
+    fn render_plain<C: Component + ?Sized>(
+        &mut self,
+        root: &C,
+        mode: DrawMode,
+        snapshots: CanvasSnapshots,
+    ) -> crate::RenderResult<(), C> {
+        let size = self.size()?;
+        let canvas = self.draw_root(root, size, mode)?;
+
+        let snapshot = match (mode, snapshots) {
+            (DrawMode::Final, _) => true,
+            (DrawMode::Normal, CanvasSnapshots::OnChange) => canvas != self.canvas_contents,
+            (DrawMode::Normal, CanvasSnapshots::Every(interval)) => self
+                .last_snapshot
+                .is_none_or(|last| last.elapsed() >= interval),
+            (DrawMode::Normal, CanvasSnapshots::Final) => false,
+        };
+        self.write_plain(snapshot.then_some(canvas))?;
+
+        Ok(())
+    }
+
+    /// Writes the emitted lines as plain text, followed by the `snapshot` of the canvas if any.
+    fn write_plain(&mut self, snapshot: Option<Lines>) -> Result<(), OutputError> {
+        fn write_lines(writer: &mut Vec<u8>, lines: impl IntoIterator<Item = Line>) {
+            for line in lines {
+                writeln!(writer, "{}", line.to_unstyled().trim_end()).unwrap();
+            }
+        }
+
+        self.stats.lines_emitted += (self.to_emit.len() + self.aux_to_emit.len()) as u64;
+        if !self.aux_to_emit.is_empty() {
+            let mut aux_buffer = Vec::new();
+            write_lines(&mut aux_buffer, std::mem::take(&mut self.aux_to_emit));
+            self.write(aux_buffer, OutputTarget::Aux)?;
+        }
+
+        let mut buffer = Vec::new();
+        write_lines(&mut buffer, std::mem::take(&mut self.to_emit));
+        self.elided = 0;
+
+        if let Some(canvas) = snapshot {
+            write_lines(&mut buffer, canvas.iter().cloned());
+            self.last_snapshot = Some(Instant::now());
+            self.canvas_contents = canvas;
+        }
+
+        if !buffer.is_empty() {
+            self.write(buffer, OutputTarget::Main)?;
+        }
+
+        Ok(())
+    }
+
     /// Helper method that makes rendering highly configurable.
     fn render_general<C: Component + ?Sized>(
         &mut self,
@@ -253,57 +859,99 @@ impl SuperConsole {
         mode: DrawMode,
         size: Dimensions,
     ) -> crate::RenderResult<(), C> {
-        /// Heuristic to determine if a buffer is too large to buffer.
-        /// Can be tuned, but is currently set to 1000000 graphemes.
-        fn is_big(buf0: &Lines, buf1: &Lines) -> bool {
-            let len0: usize = buf0.iter().map(Line::len).sum();
-            let len1: usize = buf1.iter().map(Line::len).sum();
-            (len0 + len1) > MAX_GRAPHEME_BUFFER
-        }
+        // The rest of the terminal is left for emitted messages.
+        let canvas_size = match self.config.max_canvas_height {
+            Some(max) => Dimensions::new(size.width, max.rows(size.height)),
+            None => size,
+        };
 
         // Pre-draw the frame *and then* start rendering emitted messages.
-        let mut canvas = root.draw(size, mode).map_err(crate::Error::Draw)?;
-        // We don't trust the child to not truncate the result.
-        canvas.shrink_lines_to_dimensions(size);
+        let canvas = self.draw_root(root, canvas_size, mode)?;
+
+        self.render_canvas(canvas, mode, size)?;
+
+        Ok(())
+    }
+
+    /// Writes the emitted lines that fit in the frame, followed by the drawn `canvas`.
+    fn render_canvas(
+        &mut self,
+        canvas: Lines,
+        mode: DrawMode,
+        size: Dimensions,
+    ) -> Result<(), OutputError> {
+        if mode == DrawMode::Normal
+            && matches!(
+                self.config.overflow,
+                OverflowPolicy::DropOldest | OverflowPolicy::Collapse
+            )
+            && self.buffered_graphemes() > self.config.max_buffered_graphemes
+        {
+            self.drop_oldest_emitted();
+        }
+
+        if self.config.wrap_emitted_lines {
+            // A marker split over several lines can't be replaced anymore, so leave it as is.
+            if self.elided > 0 && self.to_emit.0[0].len() > size.width {
+                self.elided = 0;
+            }
+            self.to_emit.wrap(size.width);
+            if self.output().aux_stream_is_tty() {
+                self.aux_to_emit.wrap(size.width);
+            }
+        }
+        let pending = self.to_emit.len() + self.aux_to_emit.len();
 
         // The closure to compute the limit to render the emit lines above the canvas.
         // Render at most a single frame if this not the last render.
-        // Does not buffer if there is a ridiculous amount of data.
-        let compute_limit = |buf0: &Lines, buf1: &Lines| {
-            match mode {
-                DrawMode::Normal if !is_big(buf0, buf1) => {
-                    let limit = size.height.saturating_sub(canvas.len());
-                    // arbitrary value picked so we don't starve `emit` on small terminal sizes.
-                    Some(cmp::max(limit, MINIMUM_EMIT))
-                }
-                _ => None,
+        // Dumps everything if there is a ridiculous amount of data and the policy says so.
+        let minimum_emit = self.config.minimum_emit;
+        let dump_all = self.config.overflow == OverflowPolicy::DumpAll;
+        let max_buffered_graphemes = self.config.max_buffered_graphemes;
+        let compute_limit = |console: &Self| match mode {
+            DrawMode::Normal
+                if !(dump_all && console.buffered_graphemes() > max_buffered_graphemes) =>
+            {
+                let limit = size.height.saturating_sub(canvas.len());
+                // So that we don't starve `emit` on small terminal sizes.
+                Some(cmp::max(limit, minimum_emit))
             }
+            _ => None,
         };
 
         // Render at most a single frame if this not the last render.
-        // Does not buffer if there is a ridiculous amount of data.
-        let mut limit = compute_limit(&self.to_emit, &self.aux_to_emit);
-
-        // How much of the canvas hasn't changed, so I can avoid overwriting
-        // and thus avoid flickering things like URL's in VS Code terminal.
-        let reuse_prefix = if self.to_emit.is_empty() && self.aux_to_emit.is_empty() {
-            self.canvas_contents.lines_equal(&canvas)
-        } else {
-            0
-        };
+        let mut limit = compute_limit(self);
 
         let mut buffer = Vec::new();
+        self.queue_hide_cursor(&mut buffer)?;
+
+        // If the terminal width changed, the terminal may have rewrapped the previous canvas.
+        let resized = !self.canvas_contents.is_empty() && size.width != self.canvas_size.width;
+
+        // If nothing is emitted and the terminal wasn't resized, the canvas stays where it is, so
+        // only rewrite the cells that changed. This keeps frames small and avoids flickering things
+        // like URL's in VS Code terminal.
+        if self.to_emit.is_empty() && self.aux_to_emit.is_empty() && !resized {
+            canvas.render_diff(&mut buffer, &self.canvas_contents);
+            Self::clear_canvas_post(&mut buffer)?;
+            self.canvas_contents = canvas;
+            self.canvas_size = size;
+            self.output_frame(buffer)?;
+            return Ok(());
+        }
 
-        Self::clear_canvas_pre(&mut buffer, self.canvas_contents.len() - reuse_prefix)?;
+        Self::clear_canvas_pre(&mut buffer, self.canvas_rows(size.width))?;
 
         if !self.aux_to_emit.is_empty() {
-            if self.output.aux_stream_is_tty() {
+            if self.output().aux_stream_is_tty() {
                 // If we have aux_to_emit and the aux stream is tty, we need to output the main output (stderr by default) first
                 // and flushed, so that we can make sure the all output order is correct.
-                self.output.output(buffer)?;
+                self.output_frame(buffer)?;
                 let mut aux_buffer = Vec::new();
-                limit = self.aux_to_emit.render_with_limit(&mut aux_buffer, limit);
-                self.output.output_to(aux_buffer, OutputTarget::Aux)?;
+                limit = self
+                    .aux_to_emit
+                    .render_with_limit(&mut aux_buffer, limit, size.width);
+                self.write(aux_buffer, OutputTarget::Aux)?;
 
                 // Since output is moved at `self.output.output(buffer)`, we need to new a new buffer
                 buffer = Vec::new();
@@ -311,37 +959,122 @@ impl SuperConsole {
                 // If the aux stream is not tty, we don't need to render the line, we just output to the auxillary output
                 let mut output_buffer = Vec::new();
                 self.aux_to_emit.render_raw(&mut output_buffer);
-                self.output.output_to(output_buffer, OutputTarget::Aux)?;
+                self.write(output_buffer, OutputTarget::Aux)?;
 
                 // Since we clear the aux_to_emit, we need to recompute the `limit`
-                limit = compute_limit(&self.to_emit, &self.aux_to_emit);
+                limit = compute_limit(self);
             }
         }
 
-        self.to_emit.render_with_limit(&mut buffer, limit);
+        let queued = self.to_emit.len();
+        self.to_emit
+            .render_with_limit(&mut buffer, limit, size.width);
+        if self.to_emit.len() < queued {
+            // The marker, if any, is drawn.
+            self.elided = 0;
+        }
 
-        canvas.render_from_line(&mut buffer, reuse_prefix);
+        canvas.render(&mut buffer);
         Self::clear_canvas_post(&mut buffer)?;
         self.canvas_contents = canvas;
+        self.canvas_size = size;
+        self.stats.lines_emitted += (pending - self.to_emit.len() - self.aux_to_emit.len()) as u64;
 
-        self.output.output(buffer)?;
+        self.output_frame(buffer)?;
 
         Ok(())
     }
 }
 
+/// Keeps a [`SuperConsole`] from rendering, until dropped. See [`SuperConsole::suspend`].
+#[must_use = "rendering resumes as soon as the guard is dropped"]
+pub struct SuspendGuard {
+    suspended: Arc<AtomicBool>,
+    #[cfg(unix)]
+    paused_capture: Arc<Mutex<Option<PausedCapture>>>,
+}
+
+impl SuspendGuard {
+    /// Lets the console render again. Same as dropping the guard.
+    pub fn resume(self) {}
+}
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(paused) = lock(&self.paused_capture).take() {
+            paused.resume();
+        }
+        self.suspended.store(false, Ordering::Release);
+    }
+}
+
+/// Locks `mutex`, ignoring poisoning: the paused capture is valid either way.
+#[cfg(unix)]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Drop for SuperConsole {
+    /// Best-effort restores the terminal if the console is dropped without being finalized: shows
+    /// the cursor, restores the primary screen, and emits the lines that were not drawn yet, in
+    /// place of the canvas when drawn inline.
+    fn drop(&mut self) {
+        self.forget_restored_screen();
+        self.stop_capture();
+        if self.output.is_none() {
+            return;
+        }
+        let _ignored = self.show_cursor();
+
+        self.drain_emitted(DrawMode::Final);
+        let pending = !(self.to_emit.is_empty() && self.aux_to_emit.is_empty());
+        if self.in_alternate_screen {
+            let _ignored = self.leave_alternate_screen();
+        } else if pending {
+            let _ignored = self.clear();
+        }
+
+        if !self.aux_to_emit.is_empty() {
+            let mut aux_buffer = Vec::new();
+            self.aux_to_emit.render_raw(&mut aux_buffer);
+            let _ignored = self.write(aux_buffer, OutputTarget::Aux);
+        }
+        if !self.to_emit.is_empty() {
+            let mut buffer = Vec::new();
+            let width = self.canvas_size.width;
+            self.to_emit.render_with_limit(&mut buffer, None, width);
+            let _ignored = self.write(buffer, OutputTarget::Main);
+        }
+        let _ignored = self.output().flush();
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::convert::Infallible;
+    use std::time::Duration;
+
     use anyhow::Context as _;
     use derive_more::AsRef;
 
+    use crossterm::Command;
+    use crossterm::cursor::MoveDown;
+    use crossterm::style::Attribute;
+    use crossterm::style::Color;
+    use crossterm::style::SetAttributes;
+    use crossterm::style::SetForegroundColor;
+
     use super::*;
     use crate::components::echo::Echo;
+    use crate::style::Stylize;
+    use crate::testing::SharedWriter;
     use crate::testing::SuperConsoleTestingExt;
     use crate::testing::TestOutput;
     use crate::testing::frame_contains;
     use crate::testing::test_console;
     use crate::testing::test_console_aux_incompatible;
+    use crate::testing::test_console_with_config;
 
     #[derive(AsRef, Debug)]
     #[allow(dead_code)]
@@ -382,6 +1115,211 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_max_canvas_height() -> anyhow::Result<()> {
+        for (max, rows) in [
+            (CanvasHeight::Rows(10), 10),
+            (CanvasHeight::Rows(100), 79),
+            (CanvasHeight::Fraction(0.5), 39),
+        ] {
+            let mut console = test_console_with_config(SuperConsoleConfig {
+                max_canvas_height: Some(max),
+                ..Default::default()
+            });
+            console.render(&Size)?;
+            assert!(frame_contains(
+                &console.test_output().frames[0],
+                format!("80x{rows}")
+            ));
+        }
+
+        // The rest of the terminal is used for emitted lines.
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            max_canvas_height: Some(CanvasHeight::Rows(10)),
+            ..Default::default()
+        });
+        console.emit(Lines(vec![vec!["line"].try_into()?; 100]));
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?; 100])))?;
+        assert_eq!(console.to_emit.len(), 100 - (79 - 10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let root = Echo(Lines(vec![vec!["state"].try_into()?]));
+
+        console.render(&root)?;
+        console.emit(Lines(vec![vec!["line"].try_into()?; 3]));
+        console.emit_aux(Lines(vec![vec!["aux line"].try_into()?]));
+        console.render(&root)?;
+        console.test_output_mut().should_render = false;
+        console.render(&root)?;
+
+        let stats = console.stats();
+        assert_eq!(stats.frames_rendered, 2);
+        assert_eq!(stats.frames_skipped, 1);
+        assert_eq!(stats.lines_emitted, 4);
+        assert_eq!(stats.lines_elided, 0);
+
+        let (aux, main): (Vec<_>, Vec<_>) = console
+            .test_output()
+            .frames
+            .iter()
+            .partition(|f| f.starts_with(TestOutput::aux_output_with_prefix("").as_bytes()));
+        assert_eq!(stats.main_bytes, main.iter().map(|f| f.len() as u64).sum());
+        let prefix = TestOutput::aux_output_with_prefix("").len();
+        assert_eq!(
+            stats.aux_bytes,
+            aux.iter().map(|f| (f.len() - prefix) as u64).sum()
+        );
+        assert!(stats.max_draw_time <= stats.draw_time);
+        assert!(stats.max_write_time <= stats.write_time);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_minimum_emit() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            minimum_emit: 2,
+            ..Default::default()
+        });
+        console.emit(Lines(vec![vec!["line"].try_into()?; 10]));
+        let root = Echo(Lines(vec![vec!["state"].try_into()?; 10]));
+
+        console.render_general(&root, DrawMode::Normal, Dimensions::new(100, 2))?;
+        assert_eq!(console.to_emit.len(), 8);
+
+        Ok(())
+    }
+
+    fn overflowing_console(overflow: OverflowPolicy) -> anyhow::Result<SuperConsole> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            max_buffered_graphemes: 100,
+            overflow,
+            ..Default::default()
+        });
+        // 50 lines of 7 graphemes, of which the last 14 fit.
+        for i in 0..50 {
+            console.emit(Lines(vec![vec![format!("line {i:02}")].try_into()?]));
+        }
+        Ok(console)
+    }
+
+    #[test]
+    fn test_overflow_drop_oldest() -> anyhow::Result<()> {
+        for overflow in [OverflowPolicy::DropOldest, OverflowPolicy::Collapse] {
+            let mut console = overflowing_console(overflow)?;
+            console.render_general(
+                &Echo(Lines::new()),
+                DrawMode::Normal,
+                Dimensions::new(100, 100),
+            )?;
+
+            let frame = &console.test_output().frames[0];
+            assert!(!frame_contains(frame, "line 35"));
+            assert!(frame_contains(frame, "line 36"));
+            assert!(frame_contains(frame, "line 49"));
+            let mut marker = Vec::new();
+            console.elided_marker(36).render_spans(&mut marker);
+            assert_eq!(
+                frame_contains(frame, marker),
+                overflow == OverflowPolicy::Collapse
+            );
+            assert_eq!(console.stats().lines_elided, 36);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow_collapse_merges_markers() -> anyhow::Result<()> {
+        let mut console = overflowing_console(OverflowPolicy::Collapse)?;
+        console.drop_oldest_emitted();
+        for i in 50..60 {
+            console.emit(Lines(vec![vec![format!("line {i:02}")].try_into()?]));
+        }
+        console.drop_oldest_emitted();
+
+        // The marker isn't counted as an elided line, nor kept alongside the new one.
+        assert_eq!(console.stats().lines_elided, 46);
+        assert_eq!(console.to_emit.0[0], console.elided_marker(46));
+        assert!(!console.to_emit.0[1..].contains(&console.elided_marker(36)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow_collapse_keeps_lines_like_markers() -> anyhow::Result<()> {
+        let mut console = overflowing_console(OverflowPolicy::Collapse)?;
+        console.render_general(
+            &Echo(Lines::new()),
+            DrawMode::Normal,
+            Dimensions::new(100, 100),
+        )?;
+
+        // A line reading like the marker that was drawn is not a marker to merge into the next one.
+        console.emit(Lines(vec![console.elided_marker(36)]));
+        for i in 50..64 {
+            console.emit(Lines(vec![vec![format!("line {i:02}")].try_into()?]));
+        }
+        console.drop_oldest_emitted();
+        assert_eq!(console.stats().lines_elided, 37);
+        assert_eq!(console.to_emit.0[0], console.elided_marker(1));
+        assert_eq!(console.to_emit.len(), 15);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow_collapse_marker_colors() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            max_buffered_graphemes: 100,
+            overflow: OverflowPolicy::Collapse,
+            theme: Theme::dark(),
+            color_support: ColorSupport::NoColor,
+            ..Default::default()
+        });
+        for i in 0..50 {
+            console.emit(Lines(vec![vec![format!("line {i:02}")].try_into()?]));
+        }
+        console.drop_oldest_emitted();
+
+        assert_eq!(
+            console.to_emit.0[0],
+            Line::from_iter([Span::new_unstyled("… 36 lines elided")?])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow_block() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            max_buffered_graphemes: 20,
+            minimum_emit: 1,
+            overflow: OverflowPolicy::Block,
+            ..Default::default()
+        });
+        let emitter = console.emitter();
+        for i in 0..10 {
+            emitter.emit(Lines(vec![vec![format!("line {i}")].try_into()?]))?;
+        }
+        let root = Echo(Lines(vec![vec!["state"].try_into()?; 100]));
+
+        // Lines are only taken from the queue while there is room for them.
+        console.render(&root)?;
+        assert_eq!(console.emitted_receiver.len(), 6);
+        // The lines taken are drawn a frame at a time.
+        assert_eq!(console.to_emit.len(), 3);
+
+        console.finalize(&root)?;
+
+        Ok(())
+    }
+
     /// Check that no frames are produced when should_render returns false.
     #[test]
     fn test_block_render() -> anyhow::Result<()> {
@@ -490,9 +1428,88 @@ mod tests {
                 frame_contains(frame, "http://example.com/"),
                 expect_url.contains(&i),
             );
-            assert!(frame_contains(frame, format!("number {}", i + 1)));
             assert!(frame_contains(frame, format!("special {}", i + 1)));
         }
+        // Only the changed cells of the second line are rewritten on the second frame.
+        assert!(!frame_contains(&frames[1], "number"));
+        assert!(frame_contains(&frames[1], "2, special 2"));
+        assert!(frame_contains(&frames[2], "number 3"));
+        assert!(frame_contains(&frames[3], "number 4"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_moves_to_changed_cells() -> anyhow::Result<()> {
+        let mut console = test_console();
+
+        console.render(&Echo(Lines(vec![
+            vec!["\u{280b} Building 1/40"].try_into()?,
+            vec!["stable line"].try_into()?,
+            vec!["another stable line"].try_into()?,
+        ])))?;
+        console.render(&Echo(Lines(vec![
+            vec!["\u{2819} Building 2/40"].try_into()?,
+            vec!["stable line"].try_into()?,
+            vec!["another stable line"].try_into()?,
+        ])))?;
+
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveUp(3))?;
+        expected.queue(MoveToColumn(0))?;
+        expected.extend_from_slice("\u{2819} Building 2".as_bytes());
+        expected.queue(MoveDown(3))?;
+        expected.queue(MoveToColumn(0))?;
+        expected.queue(Clear(ClearType::FromCursorDown))?;
+        expected.queue(EndSynchronizedUpdate)?;
+
+        let frame = &console.test_output().frames[1];
+        assert_eq!(
+            String::from_utf8_lossy(frame),
+            String::from_utf8_lossy(&expected)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_grows_and_shrinks() -> anyhow::Result<()> {
+        let mut console = test_console();
+
+        console.render(&Echo(Lines(vec![vec!["a"].try_into()?])))?;
+        console.render(&Echo(Lines(vec![
+            vec!["a"].try_into()?,
+            vec!["b"].try_into()?,
+            vec!["c"].try_into()?,
+        ])))?;
+
+        // New rows are created with newlines so that the terminal scrolls.
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveToColumn(0))?;
+        expected.extend_from_slice(b"b");
+        expected.queue(Clear(ClearType::UntilNewLine))?;
+        expected.push(b'\n');
+        expected.queue(MoveToColumn(0))?;
+        expected.extend_from_slice(b"c");
+        expected.queue(Clear(ClearType::UntilNewLine))?;
+        expected.push(b'\n');
+        expected.queue(MoveToColumn(0))?;
+        expected.queue(Clear(ClearType::FromCursorDown))?;
+        expected.queue(EndSynchronizedUpdate)?;
+        assert_eq!(console.test_output().frames[1], expected);
+
+        console.render(&Echo(Lines(vec![vec!["a"].try_into()?])))?;
+
+        // Removed rows are cleared, and nothing is rewritten.
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveUp(2))?;
+        expected.queue(MoveToColumn(0))?;
+        expected.queue(Clear(ClearType::FromCursorDown))?;
+        expected.queue(EndSynchronizedUpdate)?;
+        assert_eq!(console.test_output().frames[2], expected);
+
         Ok(())
     }
 
@@ -655,4 +1672,554 @@ mod tests {
 
         Ok(())
     }
+
+    /// Draws the dimensions it was given.
+    struct Size;
+
+    impl Component for Size {
+        type Error = Infallible;
+
+        fn draw_unchecked(
+            &self,
+            dimensions: Dimensions,
+            _mode: DrawMode,
+        ) -> Result<Lines, Infallible> {
+            Ok(Lines(vec![Line::sanitized(&format!(
+                "{}x{}",
+                dimensions.width, dimensions.height
+            ))]))
+        }
+    }
+
+    #[test]
+    fn test_alternate_screen() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            alternate_screen: true,
+            ..Default::default()
+        });
+
+        console.emit(Lines(vec![vec!["held back"].try_into()?]));
+        console.render(&Size)?;
+        console.render(&Echo(Lines(vec![
+            vec!["80x80"].try_into()?,
+            vec!["more"].try_into()?,
+        ])))?;
+
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(Hide)?;
+        expected.queue(EnterAlternateScreen)?;
+        expected.queue(MoveTo(0, 0))?;
+        expected.queue(Clear(ClearType::All))?;
+        expected.queue(MoveTo(0, 0))?;
+        // The root is given the whole screen.
+        expected.extend_from_slice(b"80x80");
+        expected.queue(Clear(ClearType::UntilNewLine))?;
+        expected.queue(EndSynchronizedUpdate)?;
+        assert_eq!(console.test_output().frames[0], expected);
+
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveTo(0, 1))?;
+        expected.extend_from_slice(b"more");
+        expected.queue(Clear(ClearType::UntilNewLine))?;
+        expected.queue(EndSynchronizedUpdate)?;
+        assert_eq!(console.test_output().frames[1], expected);
+
+        // Finalizing restores the primary screen, then emits the held back lines.
+        console.leave_alternate_screen()?;
+        console.render_with_mode(
+            &Echo(Lines(vec![vec!["done"].try_into()?])),
+            DrawMode::Final,
+        )?;
+
+        let frames = &console.test_output().frames;
+        let mut expected = Vec::new();
+        expected.queue(LeaveAlternateScreen)?;
+        assert_eq!(frames[2], expected);
+        assert!(frame_contains(&frames[3], "held back"));
+        assert!(frame_contains(&frames[3], "done"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_emit_limit_counts_wrapped_rows() -> anyhow::Result<()> {
+        let mut console = test_console();
+        console.test_output_mut().terminal_size = Dimensions::new(10, 10);
+
+        // Each line wraps onto 3 rows, and there are 8 rows left above the canvas.
+        for i in 0..4 {
+            console.emit(Lines(vec![vec![i.to_string().repeat(30)].try_into()?]));
+        }
+        let root = Echo(Lines(vec![vec!["state"].try_into()?]));
+        console.render(&root)?;
+        console.render(&root)?;
+
+        let frames = &console.test_output().frames;
+        assert_eq!(frames.len(), 2);
+        assert!(frame_contains(&frames[0], "0".repeat(30)));
+        assert!(frame_contains(&frames[0], "1".repeat(30)));
+        assert!(!frame_contains(&frames[0], "2".repeat(30)));
+        assert!(frame_contains(&frames[1], "2".repeat(30)));
+        assert!(frame_contains(&frames[1], "3".repeat(30)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_emit_limit_drains_tall_lines() -> anyhow::Result<()> {
+        let mut console = test_console();
+        console.test_output_mut().terminal_size = Dimensions::new(10, 10);
+
+        // A line taller than the whole screen is still emitted on its own.
+        console.emit(Lines(vec![
+            vec!["a".repeat(200)].try_into()?,
+            vec!["b"].try_into()?,
+        ]));
+        let root = Echo(Lines(vec![vec!["state"].try_into()?]));
+        console.render(&root)?;
+        console.render(&root)?;
+
+        let frames = &console.test_output().frames;
+        assert_eq!(frames.len(), 2);
+        assert!(frame_contains(&frames[0], "a".repeat(200)));
+        assert!(frame_contains(&frames[1], "b"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrap_emitted_lines() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            wrap_emitted_lines: true,
+            ..Default::default()
+        });
+        console.test_output_mut().terminal_size = Dimensions::new(10, 10);
+
+        console.emit(Lines(vec![Line::from_iter([
+            Span::new_colored_lossy(&"a".repeat(15), Color::Red),
+            Span::new_unstyled_lossy("b".repeat(10)),
+        ])]));
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+
+        let frame = &console.test_output().frames[0];
+        let mut expected = Vec::new();
+        Line::from_iter([Span::new_colored_lossy(&"a".repeat(10), Color::Red)])
+            .render_with_clear_and_nl(&mut expected);
+        Line::from_iter([
+            Span::new_colored_lossy(&"a".repeat(5), Color::Red),
+            Span::new_unstyled_lossy("b".repeat(5)),
+        ])
+        .render_with_clear_and_nl(&mut expected);
+        Line::from_iter([Span::new_unstyled_lossy("b".repeat(5))])
+            .render_with_clear_and_nl(&mut expected);
+        assert!(frame_contains(frame, expected));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_suspend() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let root = Echo(Lines(vec![vec!["state"].try_into()?]));
+
+        console.render(&root)?;
+        console.emit(Lines(vec![vec!["line 1"].try_into()?]));
+        let guard = console.suspend()?;
+
+        // The canvas is replaced by the pending lines.
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveUp(1))?;
+        expected.queue(MoveToColumn(0))?;
+        Line::unstyled("line 1")?.render_with_clear_and_nl(&mut expected);
+        expected.queue(Clear(ClearType::FromCursorDown))?;
+        expected.queue(EndSynchronizedUpdate)?;
+        assert_eq!(console.test_output().frames[1], expected);
+        let mut expected = Vec::new();
+        expected.queue(Show)?;
+        assert_eq!(console.test_output().frames[2], expected);
+
+        assert!(console.is_suspended());
+        console.emit(Lines(vec![vec!["line 2"].try_into()?]));
+        console.render(&root)?;
+        assert_eq!(console.test_output().frames.len(), 3);
+
+        guard.resume();
+        console.render(&root)?;
+        let frame = &console.test_output().frames[3];
+        assert!(frame_contains(frame, "line 2"));
+        assert!(frame_contains(frame, "state"));
+        // Nothing is moved up over what was printed while suspended.
+        assert!(!frame_contains(frame, {
+            let mut up = Vec::new();
+            up.queue(MoveUp(1))?;
+            up
+        }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hide_cursor() -> anyhow::Result<()> {
+        let mut hide = Vec::new();
+        hide.queue(Hide)?;
+        let mut show = Vec::new();
+        show.queue(Show)?;
+        let root = Echo(Lines(vec![vec!["state"].try_into()?]));
+
+        let mut console = test_console();
+        console.render(&root)?;
+        console.render(&root)?;
+        console.clear()?;
+        console.render(&root)?;
+        let frames = &console.test_output().frames;
+        let hidden: Vec<_> = frames.iter().map(|f| frame_contains(f, &hide)).collect();
+        let shown: Vec<_> = frames.iter().map(|f| frame_contains(f, &show)).collect();
+        assert_eq!(hidden, [true, false, false, true]);
+        assert_eq!(shown, [false, false, true, false]);
+
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            hide_cursor: false,
+            ..Default::default()
+        });
+        console.render(&root)?;
+        console.clear()?;
+        for frame in &console.test_output().frames {
+            assert!(!frame_contains(frame, &hide));
+            assert!(!frame_contains(frame, &show));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_shows_cursor() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut builder = crate::Builder::new();
+        builder.write_to(Box::new(writer.clone()));
+        let mut console = builder.build_forced(Dimensions::new(80, 24))?;
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+        drop(console);
+
+        let mut show = Vec::new();
+        show.queue(Show)?;
+        assert!(writer.0.lock().unwrap().ends_with(&show));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_emits_pending_lines() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let mut builder = crate::Builder::new();
+        builder.write_to(Box::new(writer.clone()));
+        let mut console = builder.build_forced(Dimensions::new(80, 24))?;
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+
+        console.emit(Lines(vec![vec!["direct"].try_into()?]));
+        console
+            .emitter()
+            .emit(Lines(vec![vec!["queued"].try_into()?]))?;
+        let written = writer.0.lock().unwrap().len();
+        drop(console);
+
+        // The canvas is cleared, and the lines drawn in its place.
+        let output = writer.0.lock().unwrap();
+        let frame = &output[written..];
+        let mut up = Vec::new();
+        up.queue(MoveUp(1))?;
+        assert!(frame_contains(frame, up));
+        assert!(frame_contains(frame, "direct"));
+        assert!(frame_contains(frame, "queued"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_aux_stream_and_size_provider() -> anyhow::Result<()> {
+        let writer = SharedWriter::default();
+        let aux_writer = SharedWriter::default();
+        let mut builder = crate::Builder::new();
+        builder
+            .write_to(Box::new(writer.clone()))
+            .aux_write_to(Box::new(aux_writer.clone()))
+            .size_provider(Box::new(|| Ok(Dimensions::new(30, 5))));
+        let mut console = builder.build_forced(Dimensions::new(80, 24))?;
+
+        console.emit_aux(Lines(vec![vec!["aux line"].try_into()?]));
+        console.render(&Size)?;
+        // The last row is left for the cursor.
+        assert!(frame_contains(&writer.0.lock().unwrap(), "30x4"));
+        assert_eq!(*aux_writer.0.lock().unwrap(), b"aux line\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_color_support() -> anyhow::Result<()> {
+        let orange = Color::Rgb {
+            r: 255,
+            g: 135,
+            b: 0,
+        };
+        let styled = || {
+            Lines(vec![Line::from_iter([Span::new_styled_lossy(
+                "styled".to_owned().with(orange).bold(),
+            )])])
+        };
+        let frames = |color_support| -> anyhow::Result<Vec<u8>> {
+            let mut console = test_console_with_config(SuperConsoleConfig {
+                color_support,
+                ..Default::default()
+            });
+            console.emit(styled());
+            console.emitter().emit(styled())?;
+            console.render(&Echo(styled()))?;
+            Ok(console.test_output().frames.concat())
+        };
+        fn ansi(command: impl Command) -> String {
+            let mut ansi = String::new();
+            command.write_ansi(&mut ansi).unwrap();
+            ansi
+        }
+        let fg = |color| ansi(SetForegroundColor(color));
+        let bold = ansi(SetAttributes(Attribute::Bold.into()));
+
+        let frame = frames(ColorSupport::TrueColor)?;
+        assert_eq!(String::from_utf8(frame)?.matches(&fg(orange)).count(), 3);
+
+        let frame = frames(ColorSupport::Ansi256)?;
+        assert_eq!(
+            String::from_utf8(frame.clone())?
+                .matches(&fg(Color::AnsiValue(208)))
+                .count(),
+            3
+        );
+        assert!(!frame_contains(&frame, fg(orange)));
+
+        let frame = frames(ColorSupport::NoColor)?;
+        assert!(!frame_contains(&frame, "\x1b[38;"));
+        assert_eq!(String::from_utf8(frame)?.matches(&bold).count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_theme_is_current_while_drawing() -> anyhow::Result<()> {
+        /// Draws with the success style of the current theme.
+        struct Success;
+
+        impl Component for Success {
+            type Error = Infallible;
+
+            fn draw_unchecked(
+                &self,
+                _dimensions: Dimensions,
+                _mode: DrawMode,
+            ) -> Result<Lines, Infallible> {
+                let style = Theme::current().success;
+                Ok(Lines(vec![Line::from_iter([Span::new_styled_lossy(
+                    style.apply("ok".to_owned()),
+                )])]))
+            }
+        }
+
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            theme: Theme::dark(),
+            ..Default::default()
+        });
+        console.render(&Success)?;
+        assert_eq!(Theme::current(), Theme::plain());
+
+        let mut green = String::new();
+        SetForegroundColor(Color::Green).write_ansi(&mut green)?;
+        assert!(frame_contains(&console.test_output().frames[0], green));
+
+        Ok(())
+    }
+
+    fn plain_console(snapshots: CanvasSnapshots) -> SuperConsole {
+        test_console_with_config(SuperConsoleConfig {
+            plain: Some(snapshots),
+            ..Default::default()
+        })
+    }
+
+    fn plain_frames(console: &SuperConsole) -> Vec<String> {
+        let frames = &console.test_output().frames;
+        assert!(frames.iter().all(|frame| !frame.contains(&0x1b)));
+        frames
+            .iter()
+            .map(|frame| String::from_utf8_lossy(frame).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_plain_snapshots_on_change() -> anyhow::Result<()> {
+        let mut console = plain_console(CanvasSnapshots::OnChange);
+
+        console.emit(Lines(vec![Line::from_iter([Span::new_colored_lossy(
+            "line 1",
+            Color::Red,
+        )])]));
+        console.emit_aux(Lines(vec![vec!["aux line"].try_into()?]));
+        console.render(&Echo(Lines(vec![vec!["state 1"].try_into()?])))?;
+        console.render(&Echo(Lines(vec![vec!["state 1"].try_into()?])))?;
+        console.render(&Echo(Lines(vec![vec!["state 2"].try_into()?])))?;
+        console.render_with_mode(
+            &Echo(Lines(vec![vec!["state 2"].try_into()?])),
+            DrawMode::Final,
+        )?;
+
+        assert_eq!(
+            plain_frames(&console),
+            [
+                TestOutput::aux_output_with_prefix("aux line\n"),
+                "line 1\nstate 1\n".to_owned(),
+                "state 2\n".to_owned(),
+                "state 2\n".to_owned(),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_snapshots_every() -> anyhow::Result<()> {
+        let mut console = plain_console(CanvasSnapshots::Every(Duration::from_secs(3600)));
+
+        console.render(&Echo(Lines(vec![vec!["state 1"].try_into()?])))?;
+        console.emit(Lines(vec![vec!["line 1"].try_into()?]));
+        console.render(&Echo(Lines(vec![vec!["state 2"].try_into()?])))?;
+        console.render_with_mode(
+            &Echo(Lines(vec![vec!["state 3"].try_into()?])),
+            DrawMode::Final,
+        )?;
+
+        assert_eq!(
+            plain_frames(&console),
+            ["state 1\n", "line 1\n", "state 3\n"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plain_snapshots_final() -> anyhow::Result<()> {
+        let mut console = plain_console(CanvasSnapshots::Final);
+
+        console.render(&Echo(Lines(vec![vec!["state 1"].try_into()?])))?;
+        console.clear()?;
+        console.render_with_mode(
+            &Echo(Lines(vec![vec!["state 2"].try_into()?])),
+            DrawMode::Final,
+        )?;
+
+        assert_eq!(plain_frames(&console), ["state 2\n"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resize_narrower() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let root = Echo(Lines(vec![
+            vec!["a".repeat(30)].try_into()?,
+            vec!["b".repeat(10)].try_into()?,
+            vec![""].try_into()?,
+        ]));
+
+        console.render(&root)?;
+        console.test_output_mut().terminal_size = Dimensions::new(20, 80);
+        console.render(&root)?;
+
+        // The first line wrapped onto a second row, so clear one more row than there are lines.
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveUp(4))?;
+        expected.queue(MoveToColumn(0))?;
+        let frame = &console.test_output().frames[1];
+        assert!(frame.starts_with(&expected));
+        assert!(frame_contains(frame, "a".repeat(20)));
+        assert!(frame_contains(frame, "b".repeat(10)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resize_wider() -> anyhow::Result<()> {
+        let mut console = test_console();
+        console.test_output_mut().terminal_size = Dimensions::new(20, 80);
+        let root = Echo(Lines(vec![
+            vec!["a".repeat(30)].try_into()?,
+            vec!["b".repeat(10)].try_into()?,
+        ]));
+
+        console.render(&root)?;
+        console.test_output_mut().terminal_size = Dimensions::new(80, 80);
+        console.render(&root)?;
+
+        // Nothing wraps, but the whole canvas is redrawn since it was truncated to the old width.
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveUp(2))?;
+        expected.queue(MoveToColumn(0))?;
+        let frame = &console.test_output().frames[1];
+        assert!(frame.starts_with(&expected));
+        assert!(frame_contains(frame, "a".repeat(30)));
+
+        // Without another resize, only the changes are drawn again.
+        console.render(&root)?;
+        assert!(!frame_contains(
+            &console.test_output().frames[2],
+            "a".repeat(30)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clear_after_resize() -> anyhow::Result<()> {
+        let mut console = test_console();
+        console.render(&Echo(Lines(vec![vec!["a".repeat(30)].try_into()?])))?;
+        console.test_output_mut().terminal_size = Dimensions::new(10, 80);
+        console.clear()?;
+
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveUp(3))?;
+        expected.queue(MoveToColumn(0))?;
+        expected.queue(Clear(ClearType::FromCursorDown))?;
+        expected.queue(Show)?;
+        expected.queue(EndSynchronizedUpdate)?;
+        assert_eq!(console.test_output().frames[1], expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resize_alternate_screen() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            alternate_screen: true,
+            ..Default::default()
+        });
+
+        console.render(&Size)?;
+        console.test_output_mut().terminal_size = Dimensions::new(40, 20);
+        console.render(&Size)?;
+
+        let mut expected = Vec::new();
+        expected.queue(BeginSynchronizedUpdate)?;
+        expected.queue(MoveTo(0, 0))?;
+        expected.queue(Clear(ClearType::All))?;
+        expected.queue(MoveTo(0, 0))?;
+        expected.extend_from_slice(b"40x20");
+        expected.queue(Clear(ClearType::UntilNewLine))?;
+        expected.queue(EndSynchronizedUpdate)?;
+        assert_eq!(console.test_output().frames[1], expected);
+
+        Ok(())
+    }
 }
@@ -10,12 +10,40 @@
 
 //! Testing utilities for Superconsole.
 use std::any::Any;
+use std::fmt::Debug;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use termwiz::cell::AttributeChange;
+use termwiz::cell::CellAttributes;
+use termwiz::escape::Action;
+use termwiz::escape::ControlCode;
+use termwiz::escape::OperatingSystemCommand;
+use termwiz::escape::csi::CSI;
+use termwiz::escape::csi::Cursor;
+use termwiz::escape::csi::DecPrivateMode;
+use termwiz::escape::csi::DecPrivateModeCode;
+use termwiz::escape::csi::Edit;
+use termwiz::escape::csi::EraseInDisplay;
+use termwiz::escape::csi::EraseInLine;
+use termwiz::escape::csi::Mode;
+use termwiz::escape::csi::Sgr;
+use termwiz::escape::parser::Parser;
+use termwiz::surface::Change;
+use termwiz::surface::CursorVisibility;
+use termwiz::surface::Position;
+use termwiz::surface::Surface;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::Dimensions;
+use crate::DrawMode;
+use crate::components::Component;
 use crate::error::OutputError;
 use crate::output::OutputTarget;
 use crate::output::SuperConsoleOutput;
 use crate::superconsole::SuperConsole;
+use crate::superconsole::SuperConsoleConfig;
 
 /// An output for testing that doesn't do real I/O.
 pub struct TestOutput {
@@ -57,7 +85,7 @@ impl SuperConsoleOutput for TestOutput {
                     .as_bytes()
                     .iter()
                     .copied()
-                    .chain(buffer.into_iter())
+                    .chain(buffer)
                     .collect::<Vec<u8>>();
                 self.frames.push(output);
                 Ok(())
@@ -94,6 +122,8 @@ pub trait SuperConsoleTestingExt {
 impl SuperConsoleTestingExt for SuperConsole {
     fn test_output(&self) -> &TestOutput {
         self.output
+            .as_deref()
+            .expect("Output was finalized")
             .as_any()
             .downcast_ref()
             .expect("Downcast failed")
@@ -101,6 +131,8 @@ impl SuperConsoleTestingExt for SuperConsole {
 
     fn test_output_mut(&mut self) -> &mut TestOutput {
         self.output
+            .as_deref_mut()
+            .expect("Output was finalized")
             .as_any_mut()
             .downcast_mut()
             .expect("Downcast failed")
@@ -108,19 +140,24 @@ impl SuperConsoleTestingExt for SuperConsole {
 }
 
 pub fn test_console() -> SuperConsole {
-    test_console_inner(true)
+    test_console_inner(true, SuperConsoleConfig::default())
 }
 
 pub fn test_console_aux_incompatible() -> SuperConsole {
-    test_console_inner(false)
+    test_console_inner(false, SuperConsoleConfig::default())
+}
+
+#[cfg(test)]
+pub(crate) fn test_console_with_config(config: SuperConsoleConfig) -> SuperConsole {
+    test_console_inner(true, config)
 }
 
-fn test_console_inner(aux_stream_is_tty: bool) -> SuperConsole {
+fn test_console_inner(aux_stream_is_tty: bool, config: SuperConsoleConfig) -> SuperConsole {
     let size = Dimensions {
         width: 80,
         height: 80,
     };
-    SuperConsole::new_with_output(
+    SuperConsole::new_with_config(
         Some(size),
         Box::new(TestOutput {
             should_render: true,
@@ -128,6 +165,7 @@ fn test_console_inner(aux_stream_is_tty: bool) -> SuperConsole {
             frames: Vec::new(),
             aux_stream_is_tty,
         }),
+        config,
     )
 }
 
@@ -151,3 +189,689 @@ pub fn assert_frame_contains(frame: &[u8], needle: impl AsRef<[u8]>) {
         );
     }
 }
+
+/// A virtual terminal, replaying the frames written to a [`TestOutput`] on a termwiz [`Surface`],
+/// so that tests can assert on what the screen would look like.
+///
+/// Only the escape sequences written by the console are understood, and line feeds also return
+/// the cursor to the first column, as with a terminal in cooked mode. Auxiliary output is ignored.
+/// Rows scrolled off the top of the screen are kept in the scrollback.
+pub struct TestTerminal {
+    parser: Parser,
+    main: Surface,
+    /// Set while the alternate screen is active.
+    alternate: Option<Surface>,
+    scrollback: Vec<termwiz::surface::Line>,
+    /// The attributes set by SGR sequences, which termwiz resets when clearing.
+    attributes: CellAttributes,
+    /// Number of rows of the canvas, right above the cursor.
+    canvas_height: usize,
+    /// Number of frames of the `TestOutput` fed so far.
+    frames_seen: usize,
+}
+
+impl TestTerminal {
+    pub fn new(size: Dimensions) -> Self {
+        Self {
+            parser: Parser::new(),
+            main: Surface::new(size.width, size.height),
+            alternate: None,
+            scrollback: Vec::new(),
+            attributes: CellAttributes::default(),
+            canvas_height: 0,
+            frames_seen: 0,
+        }
+    }
+
+    /// Feeds the frames written to the console's output since the last update, following the
+    /// size of the output and the height of the canvas. If the frames were cleared in between,
+    /// all of the remaining ones are fed.
+    pub fn update(&mut self, console: &SuperConsole) {
+        let output = console.test_output();
+        let size = output.terminal_size;
+        if self.main.dimensions() != (size.width, size.height) {
+            self.main.resize(size.width, size.height);
+            if let Some(alternate) = &mut self.alternate {
+                alternate.resize(size.width, size.height);
+            }
+        }
+        let frames = output
+            .frames
+            .get(self.frames_seen..)
+            .unwrap_or(&output.frames);
+        for frame in frames {
+            if !frame.starts_with(TestOutput::aux_prefix().as_bytes()) {
+                self.feed(frame);
+            }
+        }
+        self.frames_seen = output.frames.len();
+        self.canvas_height = console.canvas_height();
+    }
+
+    /// Interprets raw bytes written to the terminal.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let mut text = String::new();
+        for action in self.parser.parse_as_vec(bytes) {
+            match action {
+                Action::Print(c) => text.push(c),
+                Action::PrintString(s) => text.push_str(&s),
+                action => {
+                    self.print(&std::mem::take(&mut text));
+                    self.apply(action);
+                }
+            }
+        }
+        self.print(&text);
+        let seqno = self.surface().current_seqno();
+        self.surface().flush_changes_older_than(seqno);
+    }
+
+    fn surface(&mut self) -> &mut Surface {
+        self.alternate.as_mut().unwrap_or(&mut self.main)
+    }
+
+    fn screen_surface(&self) -> &Surface {
+        self.alternate.as_ref().unwrap_or(&self.main)
+    }
+
+    fn print(&mut self, text: &str) {
+        for grapheme in text.graphemes(true) {
+            let (x, _) = self.surface().cursor_position();
+            let width = self.surface().dimensions().0;
+            if x >= width {
+                // Wrap before printing, as the terminal would.
+                self.new_line();
+            }
+            self.surface().add_change(grapheme);
+        }
+    }
+
+    /// Moves to the start of the next row, scrolling the screen if the cursor is on the last one.
+    fn new_line(&mut self) {
+        let (_, y) = self.surface().cursor_position();
+        let height = self.surface().dimensions().1;
+        if y + 1 >= height && self.alternate.is_none() {
+            let top = self.main.screen_lines()[0].clone().into_owned();
+            self.scrollback.push(top);
+        }
+        self.surface().add_change("\r\n");
+    }
+
+    fn move_cursor(&mut self, x: Position, y: Position) {
+        self.surface().add_change(Change::CursorPosition { x, y });
+    }
+
+    /// Applies a clear, without moving the cursor or resetting the attributes like termwiz does.
+    fn clear(&mut self, change: Change) {
+        let (x, y) = self.surface().cursor_position();
+        let attributes = self.attributes.clone();
+        let surface = self.surface();
+        surface.add_change(change);
+        surface.add_change(Change::CursorPosition {
+            x: Position::Absolute(x),
+            y: Position::Absolute(y),
+        });
+        surface.add_change(Change::AllAttributes(attributes));
+    }
+
+    fn set_attributes(&mut self, change: impl FnOnce(&mut CellAttributes)) {
+        change(&mut self.attributes);
+        let attributes = self.attributes.clone();
+        self.surface().add_change(Change::AllAttributes(attributes));
+    }
+
+    fn apply(&mut self, action: Action) {
+        let forward = |n: u32| Position::Relative(n as isize);
+        let backward = |n: u32| Position::Relative(-(n as isize));
+        let background = self.attributes.background();
+        match action {
+            Action::Control(ControlCode::LineFeed) => self.new_line(),
+            Action::Control(ControlCode::CarriageReturn) => {
+                self.move_cursor(Position::Absolute(0), Position::Relative(0))
+            }
+            Action::CSI(CSI::Cursor(cursor)) => match cursor {
+                Cursor::Up(n) => self.move_cursor(Position::Relative(0), backward(n)),
+                Cursor::Down(n) => self.move_cursor(Position::Relative(0), forward(n)),
+                Cursor::Left(n) => self.move_cursor(backward(n), Position::Relative(0)),
+                Cursor::Right(n) => self.move_cursor(forward(n), Position::Relative(0)),
+                Cursor::CharacterAbsolute(col) | Cursor::CharacterPositionAbsolute(col) => self
+                    .move_cursor(
+                        Position::Absolute(col.as_zero_based() as usize),
+                        Position::Relative(0),
+                    ),
+                Cursor::Position { line, col } | Cursor::CharacterAndLinePosition { line, col } => {
+                    self.move_cursor(
+                        Position::Absolute(col.as_zero_based() as usize),
+                        Position::Absolute(line.as_zero_based() as usize),
+                    )
+                }
+                _ => {}
+            },
+            Action::CSI(CSI::Edit(Edit::EraseInLine(EraseInLine::EraseToEndOfLine))) => {
+                self.clear(Change::ClearToEndOfLine(background))
+            }
+            Action::CSI(CSI::Edit(Edit::EraseInDisplay(erase))) => match erase {
+                EraseInDisplay::EraseToEndOfDisplay => {
+                    self.clear(Change::ClearToEndOfScreen(background))
+                }
+                EraseInDisplay::EraseDisplay => self.clear(Change::ClearScreen(background)),
+                EraseInDisplay::EraseScrollback => self.scrollback.clear(),
+                _ => {}
+            },
+            Action::CSI(CSI::Sgr(sgr)) => self.set_attributes(|attributes| {
+                let change = match sgr {
+                    Sgr::Reset => {
+                        let hyperlink = attributes.hyperlink().cloned();
+                        *attributes = CellAttributes::default();
+                        AttributeChange::Hyperlink(hyperlink)
+                    }
+                    Sgr::Intensity(intensity) => AttributeChange::Intensity(intensity),
+                    Sgr::Underline(underline) => AttributeChange::Underline(underline),
+                    Sgr::Blink(blink) => AttributeChange::Blink(blink),
+                    Sgr::Italic(italic) => AttributeChange::Italic(italic),
+                    Sgr::Inverse(inverse) => AttributeChange::Reverse(inverse),
+                    Sgr::Invisible(invisible) => AttributeChange::Invisible(invisible),
+                    Sgr::StrikeThrough(strike) => AttributeChange::StrikeThrough(strike),
+                    Sgr::Foreground(spec) => AttributeChange::Foreground(spec.into()),
+                    Sgr::Background(spec) => AttributeChange::Background(spec.into()),
+                    _ => return,
+                };
+                attributes.apply_change(&change);
+            }),
+            Action::OperatingSystemCommand(command) => {
+                if let OperatingSystemCommand::SetHyperlink(hyperlink) = *command {
+                    self.set_attributes(|attributes| {
+                        attributes.set_hyperlink(hyperlink.map(Arc::new));
+                    });
+                }
+            }
+            Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(code)))) => {
+                self.set_mode(code, true)
+            }
+            Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(code)))) => {
+                self.set_mode(code, false)
+            }
+            _ => {}
+        }
+    }
+
+    fn set_mode(&mut self, code: DecPrivateModeCode, enabled: bool) {
+        match code {
+            DecPrivateModeCode::ShowCursor => {
+                let visibility = if enabled {
+                    CursorVisibility::Visible
+                } else {
+                    CursorVisibility::Hidden
+                };
+                self.main.add_change(Change::CursorVisibility(visibility));
+                if let Some(alternate) = &mut self.alternate {
+                    alternate.add_change(Change::CursorVisibility(visibility));
+                }
+            }
+            DecPrivateModeCode::ClearAndEnableAlternateScreen => {
+                if enabled && self.alternate.is_none() {
+                    let (width, height) = self.main.dimensions();
+                    let mut alternate = Surface::new(width, height);
+                    alternate.add_change(Change::CursorVisibility(self.main.cursor_visibility()));
+                    alternate.add_change(Change::AllAttributes(self.attributes.clone()));
+                    self.alternate = Some(alternate);
+                } else if !enabled && let Some(alternate) = self.alternate.take() {
+                    self.main
+                        .add_change(Change::CursorVisibility(alternate.cursor_visibility()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The visible rows as plain text, without trailing whitespace nor trailing blank rows.
+    pub fn screen(&self) -> Vec<String> {
+        let mut rows: Vec<String> = self
+            .screen_surface()
+            .screen_lines()
+            .iter()
+            .map(|line| line.as_str().trim_end().to_owned())
+            .collect();
+        while rows.last().is_some_and(|row| row.is_empty()) {
+            rows.pop();
+        }
+        rows
+    }
+
+    /// The visible rows, with the style of each cell.
+    pub fn screen_lines(&self) -> Vec<termwiz::surface::Line> {
+        self.screen_surface()
+            .screen_lines()
+            .into_iter()
+            .map(|line| line.into_owned())
+            .collect()
+    }
+
+    /// The rows scrolled off the top of the primary screen, oldest first, as plain text.
+    pub fn scrollback(&self) -> Vec<String> {
+        self.scrollback
+            .iter()
+            .map(|line| line.as_str().trim_end().to_owned())
+            .collect()
+    }
+
+    /// The rows scrolled off the top of the primary screen, with the style of each cell.
+    pub fn scrollback_lines(&self) -> &[termwiz::surface::Line] {
+        &self.scrollback
+    }
+
+    /// The rows of the canvas as plain text: the whole screen while on the alternate screen,
+    /// or the rows right above the cursor otherwise.
+    pub fn canvas(&self) -> Vec<String> {
+        if self.alternate.is_some() {
+            return self.screen();
+        }
+        let (_, cursor) = self.main.cursor_position();
+        let rows = self.main.screen_lines();
+        rows[cursor.saturating_sub(self.canvas_height)..cursor]
+            .iter()
+            .map(|line| line.as_str().trim_end().to_owned())
+            .collect()
+    }
+
+    /// Everything above the canvas on the primary screen, including the scrollback, as plain
+    /// text. This is where emitted lines end up.
+    pub fn emitted(&self) -> Vec<String> {
+        let (_, cursor) = self.main.cursor_position();
+        let canvas_height = if self.alternate.is_some() {
+            0
+        } else {
+            self.canvas_height
+        };
+        let rows = self.main.screen_lines();
+        let mut emitted = self.scrollback();
+        emitted.extend(
+            rows[..cursor.saturating_sub(canvas_height)]
+                .iter()
+                .map(|line| line.as_str().trim_end().to_owned()),
+        );
+        emitted
+    }
+
+    /// The position of the cursor, as column and row.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.screen_surface().cursor_position()
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.screen_surface().cursor_visibility() == CursorVisibility::Visible
+    }
+
+    pub fn in_alternate_screen(&self) -> bool {
+        self.alternate.is_some()
+    }
+
+    #[track_caller]
+    pub fn assert_screen_eq(&self, expected: &[&str]) {
+        assert_rows_eq("screen", &self.screen(), expected);
+    }
+
+    #[track_caller]
+    pub fn assert_canvas_eq(&self, expected: &[&str]) {
+        assert_rows_eq("canvas", &self.canvas(), expected);
+    }
+
+    #[track_caller]
+    pub fn assert_emitted_eq(&self, expected: &[&str]) {
+        assert_rows_eq("emitted lines", &self.emitted(), expected);
+    }
+}
+
+#[track_caller]
+fn assert_rows_eq(what: &str, rows: &[String], expected: &[&str]) {
+    if rows != expected {
+        panic!(
+            "Expected {what} to be:\n{}\nbut was:\n{}",
+            expected.join("\n"),
+            rows.join("\n")
+        );
+    }
+}
+
+/// Environment variable that makes [`assert_snapshot`] write the snapshots instead of checking them.
+pub const BLESS_ENV_VAR: &str = "SUPERCONSOLE_BLESS";
+
+/// Draws `component` and compares the result, formatted with
+/// [`Lines::fmt_for_test`](crate::Lines::fmt_for_test), to the golden file
+/// `tests/snapshots/{name}.snap` of the crate under test.
+///
+/// Run the tests with `SUPERCONSOLE_BLESS=1` to create or update the golden files.
+#[track_caller]
+pub fn assert_snapshot<C: Component + ?Sized>(
+    name: &str,
+    component: &C,
+    dimensions: Dimensions,
+    mode: DrawMode,
+) where
+    C::Error: Debug,
+{
+    let lines = component
+        .draw(dimensions, mode)
+        .unwrap_or_else(|e| panic!("Failed to draw snapshot `{name}`: {e:?}"));
+    let actual = lines.fmt_for_test().to_string();
+
+    // Cargo sets this when running tests, pointing to the crate under test rather than this one.
+    let path = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map_or_else(PathBuf::new, PathBuf::from)
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{name}.snap"));
+
+    if std::env::var_os(BLESS_ENV_VAR).is_some_and(|v| !v.is_empty() && v != "0") {
+        let dir = path.parent().expect("joined above");
+        std::fs::create_dir_all(dir)
+            .unwrap_or_else(|e| panic!("Failed to create `{}`: {e}", dir.display()));
+        std::fs::write(&path, &actual)
+            .unwrap_or_else(|e| panic!("Failed to write `{}`: {e}", path.display()));
+        return;
+    }
+
+    let expected = match std::fs::read_to_string(&path) {
+        Ok(expected) => expected,
+        Err(e) => panic!(
+            "Failed to read snapshot `{}`: {e}\nRun with {BLESS_ENV_VAR}=1 to create it. Drawn:\n{actual}",
+            path.display()
+        ),
+    };
+    if expected != actual {
+        panic!(
+            "Snapshot `{}` does not match (- expected, + actual):\n{}Run with {BLESS_ENV_VAR}=1 to update it.",
+            path.display(),
+            diff_lines(&expected, &actual)
+        );
+    }
+}
+
+/// A line diff of `expected` and `actual`, based on their longest common subsequence.
+fn diff_lines(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..].
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            writeln!(diff, "  {}", expected[i]).unwrap();
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            writeln!(diff, "- {}", expected[i]).unwrap();
+            i += 1;
+        } else {
+            writeln!(diff, "+ {}", actual[j]).unwrap();
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// A writer collecting everything into a shared buffer, so that it can be inspected once the
+/// console was moved or dropped.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct SharedWriter(pub(crate) std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl std::io::Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl crossterm::tty::IsTty for SharedWriter {
+    fn is_tty(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::Command;
+
+    use termwiz::cell::Intensity;
+    use termwiz::color::ColorAttribute;
+
+    use super::*;
+    use crate::Line;
+    use crate::Lines;
+    use crate::Span;
+    use crate::components::bordering::Bordered;
+    use crate::components::bordering::BorderedSpec;
+    use crate::components::echo::Echo;
+    use crate::style::Stylize;
+
+    fn ansi(command: impl Command) -> String {
+        let mut ansi = String::new();
+        command.write_ansi(&mut ansi).unwrap();
+        ansi
+    }
+
+    fn is_aux(frame: &[u8]) -> bool {
+        frame.starts_with(TestOutput::aux_prefix().as_bytes())
+    }
+
+    #[test]
+    fn test_frames_are_synchronized() -> anyhow::Result<()> {
+        let begin = ansi(crossterm::terminal::BeginSynchronizedUpdate);
+        let end = ansi(crossterm::terminal::EndSynchronizedUpdate);
+
+        let mut console = test_console();
+        console.render(&Echo(Lines(vec![vec!["state 1"].try_into()?])))?;
+        console.emit(Lines(vec![vec!["line 1"].try_into()?]));
+        console.emit_aux(Lines(vec![vec!["aux line 1"].try_into()?]));
+        console.render(&Echo(Lines(vec![vec!["state 2"].try_into()?])))?;
+        console.render(&Echo(Lines(vec![vec!["state 2"].try_into()?])))?;
+        console.clear()?;
+
+        let frames = &console.test_output().frames;
+        assert!(frames.iter().any(|frame| is_aux(frame)));
+        for frame in frames.iter().filter(|frame| !is_aux(frame)) {
+            let frame = String::from_utf8_lossy(frame);
+            assert!(frame.starts_with(&begin), "not bracketed: {frame:?}");
+            assert!(frame.ends_with(&end), "not bracketed: {frame:?}");
+            assert_eq!(frame.matches(&begin).count(), 1);
+            assert_eq!(frame.matches(&end).count(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_synchronized_output_disabled() -> anyhow::Result<()> {
+        let begin = ansi(crossterm::terminal::BeginSynchronizedUpdate);
+
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            synchronized_output: false,
+            ..Default::default()
+        });
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+        console.clear()?;
+
+        for frame in &console.test_output().frames {
+            assert!(!frame_contains(frame, &begin));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminal_separates_emitted_lines_from_canvas() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let mut terminal = TestTerminal::new(Dimensions::new(80, 80));
+
+        console.render(&Echo(Lines(vec![
+            vec!["state 1"].try_into()?,
+            vec!["state 2"].try_into()?,
+        ])))?;
+        console.emit(Lines(vec![
+            vec!["line 1"].try_into()?,
+            vec!["line 2"].try_into()?,
+        ]));
+        console.emit_aux(Lines(vec![vec!["aux line"].try_into()?]));
+        console.render(&Echo(Lines(vec![vec!["state 3"].try_into()?])))?;
+        terminal.update(&console);
+
+        terminal.assert_emitted_eq(&["line 1", "line 2"]);
+        terminal.assert_canvas_eq(&["state 3"]);
+        terminal.assert_screen_eq(&["line 1", "line 2", "state 3"]);
+        assert!(!terminal.cursor_visible());
+
+        console.clear()?;
+        terminal.update(&console);
+        terminal.assert_canvas_eq(&[]);
+        terminal.assert_screen_eq(&["line 1", "line 2"]);
+        assert!(terminal.cursor_visible());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminal_follows_cleared_frames() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let mut terminal = TestTerminal::new(Dimensions::new(80, 80));
+
+        console.emit(Lines(vec![vec!["line 1"].try_into()?]));
+        console.render(&Echo(Lines(vec![vec!["state 1"].try_into()?])))?;
+        console.render(&Echo(Lines(vec![vec!["state 2"].try_into()?])))?;
+        terminal.update(&console);
+
+        console.test_output_mut().frames.clear();
+        console.emit(Lines(vec![vec!["line 2"].try_into()?]));
+        console.render(&Echo(Lines(vec![vec!["state 3"].try_into()?])))?;
+        terminal.update(&console);
+
+        terminal.assert_screen_eq(&["line 1", "line 2", "state 3"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminal_scrollback() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let mut terminal = TestTerminal::new(Dimensions::new(80, 80));
+
+        let lines: Vec<String> = (0..100).map(|i| format!("line {i}")).collect();
+        console.emit(Lines(
+            lines
+                .iter()
+                .map(|line| vec![line.as_str()].try_into())
+                .collect::<Result<_, _>>()?,
+        ));
+        // Lines are emitted over several frames, only as many as fit on the screen at once.
+        for _ in 0..3 {
+            console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+        }
+        terminal.update(&console);
+
+        // 100 lines and the canvas, with the cursor on the row below it.
+        assert_eq!(terminal.scrollback(), lines[..22]);
+        assert_eq!(terminal.emitted(), lines);
+        terminal.assert_canvas_eq(&["state"]);
+        assert_eq!(terminal.cursor(), (0, 79));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminal_styles() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let mut terminal = TestTerminal::new(Dimensions::new(80, 80));
+
+        let styled = Line::from_iter([
+            Span::new_styled("bold".to_owned().bold())?,
+            Span::new_unstyled(" ")?,
+            Span::new_styled("red".to_owned().red())?,
+        ]);
+        console.emit(Lines(vec![styled]));
+        console.render(&Echo(Lines::new()))?;
+        terminal.update(&console);
+
+        terminal.assert_emitted_eq(&["bold red"]);
+        let line = &terminal.screen_lines()[0];
+        let attrs = |col| line.get_cell(col).unwrap().attrs().clone();
+        assert_eq!(attrs(0).intensity(), Intensity::Bold);
+        assert_eq!(attrs(0).foreground(), ColorAttribute::Default);
+        assert_eq!(attrs(4).intensity(), Intensity::Normal);
+        assert_eq!(attrs(5).intensity(), Intensity::Normal);
+        assert_eq!(attrs(5).foreground(), ColorAttribute::PaletteIndex(9));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_terminal_alternate_screen() -> anyhow::Result<()> {
+        let mut console = test_console_with_config(SuperConsoleConfig {
+            alternate_screen: true,
+            ..Default::default()
+        });
+        let mut terminal = TestTerminal::new(Dimensions::new(80, 80));
+        terminal.feed(b"before\n");
+
+        console.render(&Echo(Lines(vec![
+            vec!["state 1"].try_into()?,
+            vec!["state 2"].try_into()?,
+        ])))?;
+        terminal.update(&console);
+        assert!(terminal.in_alternate_screen());
+        terminal.assert_canvas_eq(&["state 1", "state 2"]);
+        terminal.assert_emitted_eq(&["before"]);
+
+        console.render(&Echo(Lines(vec![vec!["state 3"].try_into()?])))?;
+        terminal.update(&console);
+        terminal.assert_screen_eq(&["state 3"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshot() -> anyhow::Result<()> {
+        let component = Bordered::new(
+            Echo(Lines(vec![
+                vec!["Test"].try_into()?,
+                vec!["Even Longer", "ok"].try_into()?,
+            ])),
+            BorderedSpec::default(),
+        );
+        assert_snapshot(
+            "bordered",
+            &component,
+            Dimensions::new(14, 4),
+            DrawMode::Normal,
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc\nd\n", "a\nc\nx\nd\ne\n"),
+            "  a\n- b\n  c\n+ x\n  d\n+ e\n"
+        );
+        assert_eq!(diff_lines("", "a\n"), "+ a\n");
+    }
+}
@@ -0,0 +1,255 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! Styles for semantic roles, so that components don't have to hard-code colors.
+//! - Set the theme of a console with [`Builder::theme`](crate::Builder::theme).
+//! - Read it from [`Component::draw`](crate::Component::draw) with [`Theme::current`].
+
+use std::cell::Cell;
+
+use crate::style::Attribute;
+use crate::style::Color;
+use crate::style::ContentStyle;
+
+thread_local! {
+    static CURRENT: Cell<Theme> = Cell::new(Theme::plain());
+}
+
+/// The style of each semantic role. Built-in components use the roles that apply to them, such as
+/// [`border`](Theme::border) for [`Bordered`](crate::components::bordering::Bordered), and leave
+/// the rest to custom components.
+///
+/// Start from a preset and override the roles to change.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Theme {
+    /// Something that went well.
+    pub success: ContentStyle,
+    /// Something that may need attention.
+    pub warning: ContentStyle,
+    /// Something that went wrong.
+    pub error: ContentStyle,
+    /// Secondary information.
+    pub dim: ContentStyle,
+    /// Information to draw the eye to, like names or numbers.
+    pub accent: ContentStyle,
+    /// Borders drawn around components, when they don't have a style of their own.
+    pub border: ContentStyle,
+    /// The animated character of spinners.
+    pub spinner: ContentStyle,
+    /// The completed part of progress bars.
+    pub progress_fill: ContentStyle,
+    /// The remaining part of progress bars.
+    pub progress_empty: ContentStyle,
+}
+
+fn fg(color: Color) -> ContentStyle {
+    ContentStyle {
+        foreground_color: Some(color),
+        ..ContentStyle::default()
+    }
+}
+
+fn attribute(attribute: Attribute) -> ContentStyle {
+    let mut style = ContentStyle::default();
+    style.attributes.set(attribute);
+    style
+}
+
+impl Theme {
+    /// No style for any role, which is the default.
+    pub fn plain() -> Self {
+        Self::default()
+    }
+
+    /// Colors for terminals with a dark background.
+    pub fn dark() -> Self {
+        Self {
+            success: fg(Color::Green),
+            warning: fg(Color::Yellow),
+            error: ContentStyle {
+                attributes: Attribute::Bold.into(),
+                ..fg(Color::Red)
+            },
+            dim: fg(Color::DarkGrey),
+            accent: fg(Color::Cyan),
+            border: fg(Color::DarkGrey),
+            spinner: fg(Color::Cyan),
+            progress_fill: fg(Color::Green),
+            progress_empty: fg(Color::DarkGrey),
+        }
+    }
+
+    /// Colors for terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            success: fg(Color::DarkGreen),
+            warning: fg(Color::DarkYellow),
+            error: ContentStyle {
+                attributes: Attribute::Bold.into(),
+                ..fg(Color::DarkRed)
+            },
+            dim: fg(Color::DarkGrey),
+            accent: fg(Color::DarkBlue),
+            border: fg(Color::Grey),
+            spinner: fg(Color::DarkBlue),
+            progress_fill: fg(Color::DarkGreen),
+            progress_empty: fg(Color::Grey),
+        }
+    }
+
+    /// Attributes only, for terminals without colors or for users who prefer none.
+    pub fn monochrome() -> Self {
+        Self {
+            success: attribute(Attribute::Bold),
+            warning: attribute(Attribute::Underlined),
+            error: ContentStyle {
+                attributes: Attribute::Underlined.into(),
+                ..attribute(Attribute::Bold)
+            },
+            dim: attribute(Attribute::Dim),
+            accent: attribute(Attribute::Bold),
+            border: attribute(Attribute::Dim),
+            spinner: attribute(Attribute::Bold),
+            progress_fill: attribute(Attribute::Reverse),
+            progress_empty: attribute(Attribute::Dim),
+        }
+    }
+
+    /// The theme of the console drawing on this thread, or the theme set by
+    /// [`scope`](Theme::scope). The plain theme otherwise.
+    pub fn current() -> Self {
+        CURRENT.with(Cell::get)
+    }
+
+    /// Runs `f` with this theme as the current one on this thread, for instance to draw
+    /// components outside of a console.
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        /// Restores the previous theme, even if `f` panics.
+        struct Restore(Theme);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|current| current.set(self.0));
+            }
+        }
+
+        let _restore = Restore(CURRENT.with(|current| current.replace(self)));
+        f()
+    }
+
+    /// The style of a log level: errors and warnings take their roles, info takes
+    /// [`success`](Theme::success), debug [`accent`](Theme::accent) and trace [`dim`](Theme::dim).
+    /// Levels whose role is unstyled keep their built-in color, in bold.
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    pub(crate) fn level(&self, level: LogLevel) -> ContentStyle {
+        let (role, color) = match level {
+            LogLevel::Error => (self.error, Color::Red),
+            LogLevel::Warn => (self.warning, Color::Yellow),
+            LogLevel::Info => (self.success, Color::Green),
+            LogLevel::Debug => (self.accent, Color::Blue),
+            LogLevel::Trace => (self.dim, Color::Magenta),
+        };
+        if role == ContentStyle::default() {
+            ContentStyle {
+                attributes: Attribute::Bold.into(),
+                ..fg(color)
+            }
+        } else {
+            role
+        }
+    }
+
+    /// The style of the timestamp and target of log lines: [`dim`](Theme::dim), or the dim
+    /// attribute if that role is unstyled.
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    pub(crate) fn log_context(&self) -> ContentStyle {
+        if self.dim == ContentStyle::default() {
+            attribute(Attribute::Dim)
+        } else {
+            self.dim
+        }
+    }
+}
+
+/// The level of a `log` record or a `tracing` event.
+#[cfg(any(feature = "log", feature = "tracing"))]
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[cfg(feature = "log")]
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => Self::Error,
+            log::Level::Warn => Self::Warn,
+            log::Level::Info => Self::Info,
+            log::Level::Debug => Self::Debug,
+            log::Level::Trace => Self::Trace,
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl From<tracing::Level> for LogLevel {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::ERROR => Self::Error,
+            tracing::Level::WARN => Self::Warn,
+            tracing::Level::INFO => Self::Info,
+            tracing::Level::DEBUG => Self::Debug,
+            tracing::Level::TRACE => Self::Trace,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope() {
+        assert_eq!(Theme::current(), Theme::plain());
+        let inner = Theme::dark().scope(|| {
+            assert_eq!(Theme::current(), Theme::dark());
+            Theme::monochrome().scope(Theme::current)
+        });
+        assert_eq!(inner, Theme::monochrome());
+        assert_eq!(Theme::current(), Theme::plain());
+
+        let panicked = std::panic::catch_unwind(|| Theme::dark().scope(|| panic!("drawing")));
+        assert!(panicked.is_err());
+        assert_eq!(Theme::current(), Theme::plain());
+    }
+
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    #[test]
+    fn test_level_falls_back_to_built_in_colors() {
+        let bold_red = ContentStyle {
+            attributes: Attribute::Bold.into(),
+            ..fg(Color::Red)
+        };
+        assert_eq!(Theme::plain().level(LogLevel::Error), bold_red);
+        assert_eq!(Theme::plain().log_context(), attribute(Attribute::Dim));
+
+        let theme = Theme {
+            error: fg(Color::Cyan),
+            ..Theme::plain()
+        };
+        assert_eq!(theme.level(LogLevel::Error), fg(Color::Cyan));
+        assert_eq!(Theme::dark().log_context(), fg(Color::DarkGrey));
+    }
+}
@@ -0,0 +1,197 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is dual-licensed under either the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree or the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree. You may select, at your option, one of the
+ * above-listed licenses.
+ */
+
+//! A [`tracing_subscriber::Layer`] that emits events above the canvas, so that logs never tear it.
+
+use std::fmt;
+use std::fmt::Write as _;
+
+use tracing::Event;
+use tracing::Subscriber;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::layer::Layer;
+
+use crate::Line;
+use crate::Lines;
+use crate::Span;
+use crate::emitter::Emitter;
+use crate::style::StyledContent;
+
+/// Formats each event as `LEVEL target: message key=value ...`, styled with the console's
+/// [`Theme`](crate::theme::Theme), and queues it through an [`Emitter`] to be drawn on the next
+/// render.
+///
+/// Events are dropped if the emitter is bounded and full, or once the console is gone.
+pub struct SuperConsoleLayer {
+    emitter: Emitter,
+    with_target: bool,
+}
+
+impl SuperConsoleLayer {
+    pub fn new(emitter: Emitter) -> Self {
+        Self {
+            emitter,
+            with_target: true,
+        }
+    }
+
+    /// Whether to include the target of each event. Enabled by default.
+    pub fn with_target(mut self, with_target: bool) -> Self {
+        self.with_target = with_target;
+        self
+    }
+
+    fn format(&self, event: &Event<'_>) -> Lines {
+        let metadata = event.metadata();
+        let level = metadata.level();
+        let theme = self.emitter.theme();
+
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut text = visitor.message;
+        if !visitor.fields.is_empty() {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(&visitor.fields);
+        }
+
+        let mut texts = text.lines();
+        let mut first = Line::default();
+        first.push(Span::new_styled_lossy(StyledContent::new(
+            theme.level((*level).into()),
+            format!("{level:>5}"),
+        )));
+        first.push(Span::padding(1));
+        if self.with_target {
+            first.push(Span::new_styled_lossy(StyledContent::new(
+                theme.log_context(),
+                format!("{}:", metadata.target()),
+            )));
+            first.push(Span::padding(1));
+        }
+        first.push(Span::new_unstyled_lossy(texts.next().unwrap_or_default()));
+
+        let mut lines = Lines(vec![first]);
+        lines.extend(texts.map(Line::sanitized));
+        lines
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SuperConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let _ignored = self.emitter.emit(self.format(event));
+    }
+}
+
+/// Collects the message of an event, and its other fields as `key=value` pairs.
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+            return;
+        }
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={value:?}", field.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::components::echo::Echo;
+    use crate::style::Stylize;
+    use crate::testing::SuperConsoleTestingExt;
+    use crate::testing::frame_contains;
+    use crate::testing::test_console;
+
+    #[test]
+    fn test_events_are_emitted_in_order() -> anyhow::Result<()> {
+        let mut console = test_console();
+        let subscriber =
+            tracing_subscriber::registry().with(SuperConsoleLayer::new(console.emitter()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("first event");
+            tracing::warn!(count = 3, name = "x", "second event");
+            tracing::error!("multiple\nlines");
+        });
+        console.render(&Echo(Lines(vec![vec!["state"].try_into()?])))?;
+
+        let frame = console.test_output().frames.concat();
+        let text = String::from_utf8_lossy(&frame);
+        let positions: Vec<_> = [
+            " INFO",
+            "first event",
+            " WARN",
+            "second event count=3 name=\"x\"",
+            "ERROR",
+            "multiple",
+            "lines",
+            "state",
+        ]
+        .iter()
+        .map(|needle| {
+            text.find(needle)
+                .unwrap_or_else(|| panic!("missing {needle}"))
+        })
+        .collect();
+        assert!(positions.is_sorted());
+
+        let mut level = Vec::new();
+        Line::from_iter([Span::new_styled_lossy("ERROR".to_owned().red().bold())])
+            .render_spans(&mut level);
+        assert!(frame_contains(&frame, level));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_target() -> anyhow::Result<()> {
+        for with_target in [true, false] {
+            let mut console = test_console();
+            let layer = SuperConsoleLayer::new(console.emitter()).with_target(with_target);
+            tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+                tracing::info!(answer = 42)
+            });
+            console.render(&Echo(Lines::new()))?;
+
+            let frame = console.test_output().frames.concat();
+            let text = String::from_utf8_lossy(&frame);
+            assert!(text.contains("answer=42"));
+            assert_eq!(
+                text.contains("superconsole::tracing_layer::tests:"),
+                with_target
+            );
+        }
+
+        Ok(())
+    }
+}